@@ -1,9 +1,14 @@
+use std::sync::Arc;
+
 use alloy::primitives::Address;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+use super::api_key::ApiKeyScope;
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum AccessLevel {
     None,
     Basic(Address),
+    Scoped(Arc<ApiKeyScope>),
     Full,
 }
 
@@ -12,7 +17,26 @@ impl AccessLevel {
         match self {
             AccessLevel::None => false,
             AccessLevel::Basic(address) => address == user,
+            AccessLevel::Scoped(scope) => scope.allows_address(user),
             AccessLevel::Full => true,
         }
     }
+
+    /// Whether data not tied to a single address (blocks, logs, storage, ...) can be read
+    pub fn has_global_read_access(&self) -> bool {
+        match self {
+            AccessLevel::None | AccessLevel::Basic(_) => false,
+            AccessLevel::Scoped(scope) => !scope.is_address_scoped(),
+            AccessLevel::Full => true,
+        }
+    }
+
+    /// Whether the RPC method can be called, `write` if it may change state.
+    /// Only scoped API keys are limited.
+    pub fn allows_method(&self, method: &str, write: bool) -> bool {
+        match self {
+            AccessLevel::Scoped(scope) => scope.allows_method(method, write),
+            _ => true,
+        }
+    }
 }
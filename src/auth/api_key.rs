@@ -0,0 +1,340 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use alloy::hex;
+use alloy::primitives::Address;
+use chrono::DateTime;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use subtle::{Choice, ConstantTimeEq};
+
+use super::access_level::AccessLevel;
+use super::jwt::TokenError;
+
+/// Prefix of hashed keys in the config, `sha256:<salt hex>:<hash hex>`
+const HASHED_KEY_PREFIX: &str = "sha256:";
+
+/// Salt size of hashed keys in bytes
+const SALT_SIZE: usize = 16;
+
+/// Size of keys generated by the `hash-key` command
+pub const API_KEY_SIZE: usize = 40;
+
+/// Configuration for an API key with limited permissions
+#[derive(Debug, Deserialize)]
+pub struct ApiKeyConfig {
+    /// Name of the key owner, used for logging
+    pub name: String,
+    /// The key in plaintext or hashed with `hash_api_key`
+    pub key: String,
+    /// Allowed RPC methods, all methods if not set
+    pub methods: Option<Vec<String>>,
+    /// Addresses the key may access, all addresses if not set
+    pub addresses: Option<Vec<Address>>,
+    /// Reject methods that change state
+    #[serde(default)]
+    pub read_only: bool,
+    /// RFC 3339 expiry time, e.g. "2026-01-01T00:00:00Z"
+    pub expires_at: Option<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Permissions of a scoped API key
+#[derive(Debug, PartialEq)]
+pub struct ApiKeyScope {
+    pub name: String,
+    methods: Option<HashSet<String>>,
+    addresses: Option<HashSet<Address>>,
+    read_only: bool,
+    expires_at: Option<i64>,
+}
+
+impl ApiKeyScope {
+    /// Check whether the key may call `method`, `write` if the method may change state
+    pub fn allows_method(&self, method: &str, write: bool) -> bool {
+        if self.read_only && write {
+            return false;
+        }
+        match &self.methods {
+            None => true,
+            Some(methods) => methods.contains(method),
+        }
+    }
+
+    /// Check whether the key may access data of `address`
+    pub fn allows_address(&self, address: &Address) -> bool {
+        match &self.addresses {
+            None => true,
+            Some(addresses) => addresses.contains(address),
+        }
+    }
+
+    /// Whether the key is limited to some addresses
+    pub fn is_address_scoped(&self) -> bool {
+        self.addresses.is_some()
+    }
+
+    /// Unix time the key expires, if it has an expiry
+    pub fn expires_at(&self) -> Option<i64> {
+        self.expires_at
+    }
+}
+
+impl TryFrom<&ApiKeyConfig> for ApiKeyScope {
+    type Error = anyhow::Error;
+
+    fn try_from(config: &ApiKeyConfig) -> anyhow::Result<Self> {
+        let expires_at = match &config.expires_at {
+            None => None,
+            Some(t) => Some(
+                DateTime::parse_from_rfc3339(t)
+                    .map_err(|e| {
+                        anyhow::anyhow!("api key '{}': invalid expires_at: {e}", config.name)
+                    })?
+                    .timestamp(),
+            ),
+        };
+
+        Ok(Self {
+            name: config.name.clone(),
+            methods: config.methods.as_ref().map(|m| m.iter().cloned().collect()),
+            addresses: config
+                .addresses
+                .as_ref()
+                .map(|a| a.iter().copied().collect()),
+            read_only: config.read_only,
+            expires_at,
+        })
+    }
+}
+
+/// Hash `key` with a random salt, in the format accepted by `admin_keys` and `api_keys`
+pub fn hash_api_key(key: &str) -> String {
+    let salt: [u8; SALT_SIZE] = rand::random();
+    let hash = salted_hash(&salt, key);
+    format!(
+        "{HASHED_KEY_PREFIX}{}:{}",
+        hex::encode(salt),
+        hex::encode(hash)
+    )
+}
+
+fn salted_hash(salt: &[u8], key: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(salt);
+    hasher.update(key.as_bytes());
+    hasher.finalize().into()
+}
+
+/// A configured key, either in plaintext or as a salted hash
+enum StoredKey {
+    Plain(String),
+    Hashed { salt: Vec<u8>, hash: [u8; 32] },
+}
+
+impl StoredKey {
+    fn parse(key: &str) -> anyhow::Result<Self> {
+        let Some(hashed) = key.strip_prefix(HASHED_KEY_PREFIX) else {
+            return Ok(Self::Plain(key.to_owned()));
+        };
+
+        let (salt, hash) = hashed
+            .split_once(':')
+            .ok_or_else(|| anyhow::anyhow!("expected {HASHED_KEY_PREFIX}<salt>:<hash>"))?;
+        let salt = hex::decode(salt)?;
+        let hash = hex::decode(hash)?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("expected a 32 byte sha256 hash"))?;
+
+        Ok(Self::Hashed { salt, hash })
+    }
+
+    /// Compare in constant time, so the response time does not leak how much of the key matched
+    fn matches(&self, token: &str) -> Choice {
+        match self {
+            Self::Plain(key) => key.as_bytes().ct_eq(token.as_bytes()),
+            Self::Hashed { salt, hash } => hash.as_slice().ct_eq(&salted_hash(salt, token)),
+        }
+    }
+}
+
+/// Admin keys and scoped API keys, looked up by the bearer token
+#[derive(Clone, Default)]
+pub struct ApiKeys {
+    keys: Arc<Vec<(StoredKey, AccessLevel)>>,
+}
+
+impl ApiKeys {
+    /// Build from admin keys (full access) and scoped API keys, disabled keys are skipped
+    pub fn from_config(admin_keys: &[String], api_keys: &[ApiKeyConfig]) -> anyhow::Result<Self> {
+        let mut seen = HashSet::new();
+        let mut keys = Vec::new();
+
+        for (i, key) in admin_keys.iter().enumerate() {
+            let stored =
+                StoredKey::parse(key).map_err(|e| anyhow::anyhow!("admin key {i}: {e}"))?;
+            seen.insert(key.as_str());
+            keys.push((stored, AccessLevel::Full));
+        }
+
+        for config in api_keys.iter().filter(|k| k.enabled) {
+            if !seen.insert(config.key.as_str()) {
+                anyhow::bail!("api key '{}' is configured more than once", config.name);
+            }
+            let stored = StoredKey::parse(&config.key)
+                .map_err(|e| anyhow::anyhow!("api key '{}': {e}", config.name))?;
+            let scope = ApiKeyScope::try_from(config)?;
+            keys.push((stored, AccessLevel::Scoped(Arc::new(scope))));
+        }
+
+        Ok(Self {
+            keys: Arc::new(keys),
+        })
+    }
+
+    /// Look up the access level of an API key, `None` if `token` is not an API key
+    pub fn authenticate(&self, token: &str, now: i64) -> Option<Result<AccessLevel, TokenError>> {
+        // Check every key without returning early, so the timing does not depend on which key matched
+        let mut found = None;
+        for (key, access) in self.keys.iter() {
+            if bool::from(key.matches(token)) && found.is_none() {
+                found = Some(access);
+            }
+        }
+        let access = found?;
+
+        if let AccessLevel::Scoped(scope) = access
+            && scope.expires_at().is_some_and(|exp| exp <= now)
+        {
+            return Some(Err(TokenError::Expired));
+        }
+
+        Some(Ok(access.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api_key(key: &str) -> ApiKeyConfig {
+        ApiKeyConfig {
+            name: "partner".to_string(),
+            key: key.to_string(),
+            methods: None,
+            addresses: None,
+            read_only: false,
+            expires_at: None,
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_api_key_scopes() {
+        let address = Address::repeat_byte(0x11);
+        let now = DateTime::parse_from_rfc3339("2025-07-01T00:00:00Z")
+            .unwrap()
+            .timestamp();
+
+        let keys = ApiKeys::from_config(
+            &["admin".to_string()],
+            &[
+                ApiKeyConfig {
+                    methods: Some(vec!["eth_getBalance".to_string()]),
+                    addresses: Some(vec![address]),
+                    ..api_key("balance-only")
+                },
+                ApiKeyConfig {
+                    read_only: true,
+                    ..api_key("read-only")
+                },
+                ApiKeyConfig {
+                    expires_at: Some("2025-06-30T00:00:00Z".to_string()),
+                    ..api_key("expired")
+                },
+                ApiKeyConfig {
+                    enabled: false,
+                    ..api_key("disabled")
+                },
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            keys.authenticate("admin", now).unwrap().unwrap(),
+            AccessLevel::Full
+        );
+        assert!(keys.authenticate("unknown", now).is_none());
+        assert!(keys.authenticate("disabled", now).is_none());
+        assert!(matches!(
+            keys.authenticate("expired", now).unwrap(),
+            Err(TokenError::Expired)
+        ));
+
+        let access = keys.authenticate("balance-only", now).unwrap().unwrap();
+        assert!(access.allows_method("eth_getBalance", false));
+        assert!(!access.allows_method("eth_getCode", false));
+        assert!(access.is_authorized(&address));
+        assert!(!access.is_authorized(&Address::ZERO));
+        assert!(!access.has_global_read_access());
+
+        let access = keys.authenticate("read-only", now).unwrap().unwrap();
+        assert!(access.allows_method("eth_getCode", false));
+        assert!(!access.allows_method("eth_sendRawTransaction", true));
+        assert!(access.is_authorized(&Address::ZERO));
+        assert!(access.has_global_read_access());
+
+        // Invalid expiry
+        let err = ApiKeys::from_config(
+            &[],
+            &[ApiKeyConfig {
+                expires_at: Some("tomorrow".to_string()),
+                ..api_key("invalid")
+            }],
+        )
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("invalid expires_at"));
+    }
+
+    #[test]
+    fn test_hashed_api_keys() {
+        let admin_hash = hash_api_key("admin");
+        assert!(admin_hash.starts_with(HASHED_KEY_PREFIX));
+        // Random salt
+        assert_ne!(admin_hash, hash_api_key("admin"));
+
+        let keys = ApiKeys::from_config(
+            &[admin_hash],
+            &[ApiKeyConfig {
+                read_only: true,
+                ..api_key(&hash_api_key("partner"))
+            }],
+        )
+        .unwrap();
+
+        assert_eq!(
+            keys.authenticate("admin", 0).unwrap().unwrap(),
+            AccessLevel::Full
+        );
+        let access = keys.authenticate("partner", 0).unwrap().unwrap();
+        assert!(!access.allows_method("eth_sendRawTransaction", true));
+        assert!(keys.authenticate("admi", 0).is_none());
+        assert!(keys.authenticate("", 0).is_none());
+
+        // The hash itself is not a valid key
+        let hash = hash_api_key("secret");
+        let keys = ApiKeys::from_config(std::slice::from_ref(&hash), &[]).unwrap();
+        assert!(keys.authenticate(&hash, 0).is_none());
+
+        // Malformed hashes
+        for key in ["sha256:00", "sha256:zz:00", "sha256:00:0011"] {
+            assert!(ApiKeys::from_config(&[key.to_string()], &[]).is_err());
+        }
+    }
+}
@@ -1,38 +1,102 @@
-use dashmap::DashSet;
+use chrono::Utc;
 use futures_util::future::BoxFuture;
 use headers::{Authorization, HeaderMapExt, authorization::Bearer};
+use hyper::StatusCode;
+use hyper::header::WWW_AUTHENTICATE;
 use jsonrpsee::http_client::{HeaderMap, HttpBody, HttpRequest, HttpResponse};
+use jsonrpsee::server::ws::is_upgrade_request;
 use tower_http::auth::AsyncAuthorizeRequest;
 
 use super::access_level::AccessLevel;
-use super::jwt::JwtSigner;
+use super::api_key::ApiKeys;
+use super::jwt::{JwtSigner, TokenError};
+use super::revocation::SharedRevocationList;
+use super::session::{Session, WebSocketConnection, set_session};
+use crate::metrics::metrics;
 
 #[derive(Clone)]
 pub struct AuthenticationMiddleware {
     jwt: JwtSigner,
-    api_keys: DashSet<String>,
+    api_keys: ApiKeys,
+    revoked_tokens: SharedRevocationList,
+    // Reject invalid tokens with 401 instead of treating them as anonymous requests
+    reject_invalid_tokens: bool,
 }
 
 impl AuthenticationMiddleware {
-    pub fn new(jwt: JwtSigner, api_keys: DashSet<String>) -> Self {
-        Self { jwt, api_keys }
+    pub fn new(
+        jwt: JwtSigner,
+        api_keys: ApiKeys,
+        revoked_tokens: SharedRevocationList,
+        reject_invalid_tokens: bool,
+    ) -> Self {
+        Self {
+            jwt,
+            api_keys,
+            revoked_tokens,
+            reject_invalid_tokens,
+        }
     }
 
-    async fn authenticate_user(&self, headers: &HeaderMap) -> AccessLevel {
-        let token = match headers.typed_get::<Authorization<Bearer>>() {
-            Some(Authorization(bearer)) => bearer.token().to_string(),
-            _ => return AccessLevel::None,
-        };
-
-        if self.api_keys.contains(&token) {
-            return AccessLevel::Full;
+    /// Authenticate a bearer token, either an API key or a JWT
+    pub async fn authenticate_token(&self, token: &str) -> Result<Session, TokenError> {
+        if let Some(access) = self.api_keys.authenticate(token, Utc::now().timestamp()) {
+            let access = access?;
+            let expires_at = match &access {
+                AccessLevel::Scoped(scope) => scope.expires_at(),
+                _ => None,
+            };
+            return Ok(Session {
+                access,
+                expires_at,
+                token_id: None,
+            });
         }
 
-        match self.jwt.decode_token(&token) {
-            Ok(claims) => AccessLevel::Basic(claims.address),
-            Err(_) => AccessLevel::None,
+        let claims = self.jwt.decode_token(token)?;
+
+        // token was revoked with `siwe_signOut`
+        if let Some(jti) = &claims.jti
+            && self.revoked_tokens.is_revoked(jti).await
+        {
+            return Err(TokenError::Revoked);
         }
+
+        Ok(Session {
+            access: AccessLevel::Basic(claims.address),
+            expires_at: Some(claims.exp as i64),
+            token_id: claims.jti,
+        })
     }
+
+    /// Authenticate a request, requests without a bearer token are anonymous
+    async fn authenticate(&self, headers: &HeaderMap) -> Result<Session, TokenError> {
+        let result = match headers.typed_get::<Authorization<Bearer>>() {
+            Some(Authorization(bearer)) => self.authenticate_token(bearer.token()).await,
+            _ => Ok(Session::anonymous()),
+        };
+        metrics().record_auth(result.as_ref().map(|session| &session.access));
+        result
+    }
+
+    async fn authenticate_user(&self, headers: &HeaderMap) -> Session {
+        self.authenticate(headers)
+            .await
+            .unwrap_or_else(|_| Session::anonymous())
+    }
+}
+
+/// Build a `401 Unauthorized` response as described in RFC 6750
+fn invalid_token_response(err: &TokenError) -> HttpResponse {
+    let challenge = format!(
+        "Bearer error=\"invalid_token\", error_description=\"{}\"",
+        err.reason()
+    );
+    http::Response::builder()
+        .status(StatusCode::UNAUTHORIZED)
+        .header(WWW_AUTHENTICATE, challenge)
+        .body(HttpBody::empty())
+        .expect("valid response")
 }
 
 impl AsyncAuthorizeRequest<HttpBody> for AuthenticationMiddleware {
@@ -43,8 +107,22 @@ impl AsyncAuthorizeRequest<HttpBody> for AuthenticationMiddleware {
     fn authorize(&mut self, mut request: HttpRequest) -> Self::Future {
         let self_clone = self.clone();
         Box::pin(async move {
-            let access = self_clone.authenticate_user(request.headers()).await;
-            request.extensions_mut().insert(access); // pass to rpc handler
+            let session = if self_clone.reject_invalid_tokens {
+                match self_clone.authenticate(request.headers()).await {
+                    Ok(session) => session,
+                    Err(e) => {
+                        debug!("Rejecting invalid token: {e}");
+                        return Err(invalid_token_response(&e));
+                    }
+                }
+            } else {
+                self_clone.authenticate_user(request.headers()).await
+            };
+            // WebSocket calls get the extensions of the upgrade request
+            if is_upgrade_request(&request) {
+                request.extensions_mut().insert(WebSocketConnection);
+            }
+            set_session(request.extensions_mut(), session); // pass to rpc handler
             Ok(request)
         })
     }
@@ -54,7 +132,12 @@ impl AsyncAuthorizeRequest<HttpBody> for AuthenticationMiddleware {
 mod tests {
     use super::*;
     use headers::HeaderValue;
+    use hyper::header::{CONNECTION, UPGRADE};
     use jsonrpsee::http_client::HeaderMap;
+    use std::sync::Arc;
+
+    use crate::auth::revocation::RevocationList;
+    use crate::auth::session::AccessExpiry;
 
     #[tokio::test]
     async fn test_admin_key_access() {
@@ -65,22 +148,25 @@ mod tests {
         ];
 
         // Initialize admin keys
-        let set = dashmap::DashSet::default();
-        for key in &admin_keys {
-            set.insert(key.clone());
-        }
+        let api_keys = ApiKeys::from_config(&admin_keys, &[]).unwrap();
 
         // Create a dummy JwtSigner (not used for admin key test)
         let signer = crate::auth::JwtSigner::from_config(
             &[crate::auth::JwtSignerKeyConfig {
                 kid: "test".to_string(),
-                secret: "testsecret".to_string(),
+                secret: "testsecret-testsecret-testsecret".to_string(),
+                ..Default::default()
             }],
             "test",
         )
         .unwrap();
 
-        let mw = AuthenticationMiddleware::new(signer, set);
+        let mw = AuthenticationMiddleware::new(
+            signer,
+            api_keys,
+            Arc::new(crate::auth::InMemoryRevocationList::new()),
+            false,
+        );
 
         // ----------- Test with admin key -----------
         let admin_key = &admin_keys[0];
@@ -89,7 +175,7 @@ mod tests {
         map.insert("authorization", value);
 
         // Should grant Full access
-        let access = mw.authenticate_user(&map).await;
+        let access = mw.authenticate_user(&map).await.access;
         assert_eq!(access, crate::auth::AccessLevel::Full);
 
         // ----------- Test with non-admin key -----------
@@ -98,7 +184,127 @@ mod tests {
         map2.insert("authorization", value2);
 
         // Should grant None access
-        let access2 = mw.authenticate_user(&map2).await;
+        let access2 = mw.authenticate_user(&map2).await.access;
         assert_eq!(access2, crate::auth::AccessLevel::None);
     }
+
+    #[tokio::test]
+    async fn test_revoked_token() {
+        let signer = crate::auth::JwtSigner::from_config(
+            &[crate::auth::JwtSignerKeyConfig {
+                kid: "test".to_string(),
+                secret: "testsecret-testsecret-testsecret".to_string(),
+                ..Default::default()
+            }],
+            "test",
+        )
+        .unwrap();
+        let revoked_tokens = Arc::new(crate::auth::InMemoryRevocationList::new());
+        let mw = AuthenticationMiddleware::new(
+            signer.clone(),
+            ApiKeys::default(),
+            revoked_tokens.clone(),
+            false,
+        );
+
+        let address = alloy::primitives::Address::repeat_byte(0x11);
+        let exp = (chrono::Utc::now().timestamp() + 3600) as usize;
+        let token = signer.create_token(address, exp).unwrap();
+        let mut map = HeaderMap::new();
+        let value = HeaderValue::from_str(&format!("Bearer {}", token)).unwrap();
+        map.insert("authorization", value);
+
+        // Valid token grants Basic access until it expires
+        let session = mw.authenticate_user(&map).await;
+        assert_eq!(session.access, crate::auth::AccessLevel::Basic(address));
+        assert_eq!(session.expires_at, Some(exp as i64));
+
+        // Revoked token grants None access
+        let claims = signer.decode_token(&token).unwrap();
+        revoked_tokens.revoke(claims.jti.unwrap(), claims.exp).await;
+        let access = mw.authenticate_user(&map).await.access;
+        assert_eq!(access, crate::auth::AccessLevel::None);
+    }
+
+    #[tokio::test]
+    async fn test_reject_invalid_tokens() {
+        let signer = crate::auth::JwtSigner::from_config(
+            &[crate::auth::JwtSignerKeyConfig {
+                kid: "test".to_string(),
+                secret: "testsecret-testsecret-testsecret".to_string(),
+                ..Default::default()
+            }],
+            "test",
+        )
+        .unwrap();
+        let mut mw = AuthenticationMiddleware::new(
+            signer.clone(),
+            ApiKeys::default(),
+            Arc::new(crate::auth::InMemoryRevocationList::new()),
+            true,
+        );
+
+        let request = |token: Option<&str>| {
+            let mut builder = http::Request::builder();
+            if let Some(token) = token {
+                builder = builder.header("authorization", format!("Bearer {token}"));
+            }
+            builder.body(HttpBody::empty()).unwrap()
+        };
+        let address = alloy::primitives::Address::repeat_byte(0x11);
+
+        // Valid token
+        let exp = (chrono::Utc::now().timestamp() + 3600) as usize;
+        let token = signer.create_token(address, exp).unwrap();
+        let req = mw.authorize(request(Some(&token))).await.unwrap();
+        assert_eq!(
+            req.extensions().get::<AccessLevel>(),
+            Some(&AccessLevel::Basic(address))
+        );
+        assert_eq!(
+            req.extensions().get::<AccessExpiry>(),
+            Some(&AccessExpiry(exp as i64))
+        );
+        assert!(req.extensions().get::<WebSocketConnection>().is_none());
+
+        // The upgrade header alone is not a WebSocket upgrade
+        let mut ws_request = request(Some(&token));
+        ws_request
+            .headers_mut()
+            .insert(UPGRADE, "websocket".parse().unwrap());
+        let req = mw.authorize(ws_request).await.unwrap();
+        assert!(req.extensions().get::<WebSocketConnection>().is_none());
+
+        // WebSocket upgrade
+        let mut ws_request = request(Some(&token));
+        ws_request
+            .headers_mut()
+            .insert(UPGRADE, "websocket".parse().unwrap());
+        ws_request
+            .headers_mut()
+            .insert(CONNECTION, "Upgrade".parse().unwrap());
+        let req = mw.authorize(ws_request).await.unwrap();
+        assert!(req.extensions().get::<WebSocketConnection>().is_some());
+
+        // No token
+        let req = mw.authorize(request(None)).await.unwrap();
+        assert_eq!(
+            req.extensions().get::<AccessLevel>(),
+            Some(&AccessLevel::None)
+        );
+
+        // Expired token
+        let exp = (chrono::Utc::now().timestamp() - 3600) as usize;
+        let token = signer.create_token(address, exp).unwrap();
+        let resp = mw.authorize(request(Some(&token))).await.unwrap_err();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            resp.headers()[WWW_AUTHENTICATE],
+            "Bearer error=\"invalid_token\", error_description=\"token expired\""
+        );
+
+        // Garbage token
+        let resp = mw.authorize(request(Some("garbage"))).await.unwrap_err();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
 }
@@ -0,0 +1,70 @@
+use alloy::primitives::{Address, B256, Bytes};
+use alloy::rpc::types::{TransactionInput, TransactionRequest};
+use alloy::sol;
+use alloy::sol_types::SolCall;
+use jsonrpsee::core::ClientError;
+use jsonrpsee::core::client::ClientT;
+use jsonrpsee::http_client::HttpClient;
+use jsonrpsee::rpc_params;
+
+sol! {
+    interface IERC1271 {
+        function isValidSignature(bytes32 hash, bytes signature) external view returns (bytes4 magicValue);
+    }
+}
+
+/// `bytes4(keccak256("isValidSignature(bytes32,bytes)"))`
+pub const EIP1271_MAGIC_VALUE: [u8; 4] = [0x16, 0x26, 0xba, 0x7e];
+
+/// Whether `output` is the ABI encoded magic value, a `bytes4` padded to 32 bytes
+fn is_magic_value(output: &[u8]) -> bool {
+    output.len() == 32 && output[..4] == EIP1271_MAGIC_VALUE && output[4..].iter().all(|b| *b == 0)
+}
+
+/// Check a contract wallet signature by calling `isValidSignature` on the wallet
+pub async fn verify_eip1271(
+    client: &HttpClient,
+    wallet: Address,
+    hash: B256,
+    signature: &Bytes,
+) -> Result<bool, ClientError> {
+    let input = IERC1271::isValidSignatureCall {
+        hash,
+        signature: signature.clone(),
+    }
+    .abi_encode();
+
+    let request = TransactionRequest::default()
+        .to(wallet)
+        .input(TransactionInput::new(input.into()));
+
+    match client
+        .request::<Bytes, _>("eth_call", rpc_params![request, "latest"])
+        .await
+    {
+        Ok(output) => Ok(is_magic_value(&output)),
+        // wallets may revert on invalid signatures
+        Err(ClientError::Call(_)) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_magic_value() {
+        let mut output = [0u8; 32];
+        output[..4].copy_from_slice(&EIP1271_MAGIC_VALUE);
+        assert!(is_magic_value(&output));
+
+        // Short, overlong and dirty outputs
+        assert!(!is_magic_value(&EIP1271_MAGIC_VALUE));
+        assert!(!is_magic_value(&output[..31]));
+        assert!(!is_magic_value(&[output, [0u8; 32]].concat()));
+        output[31] = 1;
+        assert!(!is_magic_value(&output));
+        assert!(!is_magic_value(&[0u8; 32]));
+    }
+}
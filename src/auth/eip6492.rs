@@ -0,0 +1,238 @@
+use alloy::primitives::{Address, B256, Bytes};
+use alloy::rpc::types::{TransactionInput, TransactionRequest};
+use alloy::sol_types::{SolCall, SolValue};
+use jsonrpsee::core::ClientError;
+use jsonrpsee::core::client::ClientT;
+use jsonrpsee::http_client::HttpClient;
+use jsonrpsee::rpc_params;
+
+use super::eip1271::{EIP1271_MAGIC_VALUE, IERC1271};
+
+/// Suffix of EIP-6492 wrapped signatures, `0x6492` repeated to 32 bytes
+pub const EIP6492_MAGIC_SUFFIX: [u8; 32] = [
+    0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92,
+    0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92, 0x64, 0x92,
+];
+
+/// Length of the validator code before the appended call data, see `validator_code`
+const VALIDATOR_PREFIX_LEN: usize = 93;
+
+/// A signature of a smart account that might not be deployed yet,
+/// `abi.encode(factory, factoryCalldata, signature) ++ magicSuffix`
+#[derive(Debug, PartialEq)]
+pub struct Eip6492Signature {
+    pub factory: Address,
+    pub factory_calldata: Bytes,
+    pub signature: Bytes,
+}
+
+impl Eip6492Signature {
+    /// Check whether `signature` carries the EIP-6492 magic suffix
+    pub fn is_wrapped(signature: &[u8]) -> bool {
+        signature.ends_with(&EIP6492_MAGIC_SUFFIX)
+    }
+
+    /// Decode a wrapped signature, returns `None` if it is not a valid EIP-6492 signature
+    pub fn decode(signature: &[u8]) -> Option<Self> {
+        let data = signature.strip_suffix(&EIP6492_MAGIC_SUFFIX)?;
+        let (factory, factory_calldata, signature) =
+            <(Address, Bytes, Bytes)>::abi_decode_params(data).ok()?;
+
+        Some(Self {
+            factory,
+            factory_calldata,
+            signature,
+        })
+    }
+}
+
+/// Build the init code of a throwaway contract that calls `factory` to deploy the wallet
+/// (ignoring failures, the wallet might be deployed already), then calls the wallet and
+/// returns `(returndata[0..32], success)`.
+///
+/// The call data of both calls is appended to the code, so that the whole check fits in
+/// a single `eth_call` without a `to` address.
+fn validator_code(
+    factory: Address,
+    factory_calldata: &[u8],
+    wallet: Address,
+    wallet_calldata: &[u8],
+) -> Option<Bytes> {
+    const PUSH1: u8 = 0x60;
+    const PUSH2: u8 = 0x61;
+    const PUSH20: u8 = 0x73;
+    const CODECOPY: u8 = 0x39;
+    const CALL: u8 = 0xf1;
+    const STATICCALL: u8 = 0xfa;
+    const GAS: u8 = 0x5a;
+    const POP: u8 = 0x50;
+    const MSTORE: u8 = 0x52;
+    const RETURN: u8 = 0xf3;
+
+    // call data is copied to memory at 0x20, the wallet return data is written to 0x00
+    const ARGS: u8 = 0x20;
+
+    let factory_len = u16::try_from(factory_calldata.len()).ok()?;
+    let wallet_len = u16::try_from(wallet_calldata.len()).ok()?;
+    let factory_offset = u16::try_from(VALIDATOR_PREFIX_LEN).ok()?;
+    let wallet_offset = factory_offset.checked_add(factory_len)?;
+
+    let mut code =
+        Vec::with_capacity(VALIDATOR_PREFIX_LEN + factory_calldata.len() + wallet_calldata.len());
+
+    // factory.call(factory_calldata)
+    code.push(PUSH2);
+    code.extend_from_slice(&factory_len.to_be_bytes());
+    code.push(PUSH2);
+    code.extend_from_slice(&factory_offset.to_be_bytes());
+    code.extend_from_slice(&[PUSH1, ARGS, CODECOPY]);
+    code.extend_from_slice(&[PUSH1, 0x00, PUSH1, 0x00]); // retSize, retOffset
+    code.push(PUSH2);
+    code.extend_from_slice(&factory_len.to_be_bytes());
+    code.extend_from_slice(&[PUSH1, ARGS, PUSH1, 0x00]); // argsOffset, value
+    code.push(PUSH20);
+    code.extend_from_slice(factory.as_slice());
+    code.extend_from_slice(&[GAS, CALL, POP]);
+
+    // wallet.staticcall(wallet_calldata)
+    code.push(PUSH2);
+    code.extend_from_slice(&wallet_len.to_be_bytes());
+    code.push(PUSH2);
+    code.extend_from_slice(&wallet_offset.to_be_bytes());
+    code.extend_from_slice(&[PUSH1, ARGS, CODECOPY]);
+    code.extend_from_slice(&[PUSH1, 0x20, PUSH1, 0x00]); // retSize, retOffset
+    code.push(PUSH2);
+    code.extend_from_slice(&wallet_len.to_be_bytes());
+    code.extend_from_slice(&[PUSH1, ARGS]); // argsOffset
+    code.push(PUSH20);
+    code.extend_from_slice(wallet.as_slice());
+    code.extend_from_slice(&[GAS, STATICCALL]);
+
+    // mstore(0x20, success); return(0x00, 0x40)
+    code.extend_from_slice(&[PUSH1, 0x20, MSTORE, PUSH1, 0x40, PUSH1, 0x00, RETURN]);
+    debug_assert_eq!(code.len(), VALIDATOR_PREFIX_LEN);
+
+    code.extend_from_slice(factory_calldata);
+    code.extend_from_slice(wallet_calldata);
+    Some(code.into())
+}
+
+/// Check an EIP-6492 signature by simulating the wallet deployment and
+/// the `isValidSignature` call in a single `eth_call`
+pub async fn verify_eip6492(
+    client: &HttpClient,
+    wallet: Address,
+    hash: B256,
+    signature: &Eip6492Signature,
+) -> Result<bool, ClientError> {
+    let wallet_calldata = IERC1271::isValidSignatureCall {
+        hash,
+        signature: signature.signature.clone(),
+    }
+    .abi_encode();
+
+    let code = match validator_code(
+        signature.factory,
+        &signature.factory_calldata,
+        wallet,
+        &wallet_calldata,
+    ) {
+        Some(code) => code,
+        None => return Ok(false), // too large
+    };
+
+    // no `to` address, the code is executed as contract creation
+    let request = TransactionRequest::default().input(TransactionInput::new(code));
+
+    match client
+        .request::<Bytes, _>("eth_call", rpc_params![request, "latest"])
+        .await
+    {
+        Ok(output) => {
+            Ok(output.len() == 64 && output.starts_with(&EIP1271_MAGIC_VALUE) && output[63] == 1)
+        }
+        // e.g. the simulated deployment ran out of gas
+        Err(ClientError::Call(_)) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// Wrap a signature as described in EIP-6492
+    pub fn wrap(factory: Address, factory_calldata: &Bytes, signature: &Bytes) -> Bytes {
+        let mut wrapped =
+            (factory, factory_calldata.clone(), signature.clone()).abi_encode_params();
+        wrapped.extend_from_slice(&EIP6492_MAGIC_SUFFIX);
+        wrapped.into()
+    }
+
+    /// Extract `(factory, factory_calldata, wallet, wallet_calldata)` from the validator code
+    pub fn parse_validator_code(code: &[u8]) -> (Address, Bytes, Address, Bytes) {
+        let factory_len = u16::from_be_bytes([code[1], code[2]]) as usize;
+        let wallet_len = u16::from_be_bytes([code[45], code[46]]) as usize;
+        let factory = Address::from_slice(&code[21..41]);
+        let wallet = Address::from_slice(&code[63..83]);
+        let factory_calldata = &code[VALIDATOR_PREFIX_LEN..VALIDATOR_PREFIX_LEN + factory_len];
+        let wallet_calldata = &code[VALIDATOR_PREFIX_LEN + factory_len..];
+        assert_eq!(wallet_calldata.len(), wallet_len);
+        (
+            factory,
+            Bytes::copy_from_slice(factory_calldata),
+            wallet,
+            Bytes::copy_from_slice(wallet_calldata),
+        )
+    }
+
+    #[test]
+    fn test_eip6492_signature_decode() {
+        let factory = Address::repeat_byte(0x11);
+        let factory_calldata = Bytes::from(vec![0x22; 68]);
+        let signature = Bytes::from(vec![0x33; 65]);
+
+        let wrapped = wrap(factory, &factory_calldata, &signature);
+        assert!(Eip6492Signature::is_wrapped(&wrapped));
+        assert_eq!(
+            Eip6492Signature::decode(&wrapped),
+            Some(Eip6492Signature {
+                factory,
+                factory_calldata,
+                signature: signature.clone(),
+            })
+        );
+
+        // Not wrapped
+        assert!(!Eip6492Signature::is_wrapped(&signature));
+        assert_eq!(Eip6492Signature::decode(&signature), None);
+
+        // Magic suffix without a valid wrapper
+        let mut invalid = vec![0x44; 10];
+        invalid.extend_from_slice(&EIP6492_MAGIC_SUFFIX);
+        assert!(Eip6492Signature::is_wrapped(&invalid));
+        assert_eq!(Eip6492Signature::decode(&invalid), None);
+    }
+
+    #[test]
+    fn test_validator_code_layout() {
+        let factory = Address::repeat_byte(0x11);
+        let wallet = Address::repeat_byte(0x55);
+        let factory_calldata = Bytes::from(vec![0x22; 68]);
+        let wallet_calldata = Bytes::from(vec![0x66; 196]);
+
+        let code = validator_code(factory, &factory_calldata, wallet, &wallet_calldata).unwrap();
+        assert_eq!(
+            code.len(),
+            VALIDATOR_PREFIX_LEN + factory_calldata.len() + wallet_calldata.len()
+        );
+        assert_eq!(
+            parse_validator_code(&code),
+            (factory, factory_calldata, wallet, wallet_calldata)
+        );
+
+        // Call data offsets must fit in PUSH2
+        let too_large = vec![0u8; u16::MAX as usize + 1];
+        assert!(validator_code(factory, &too_large, wallet, &[]).is_none());
+    }
+}
@@ -1,6 +1,7 @@
 use jsonrpsee::types::ErrorObjectOwned;
 use jsonrpsee::types::error::{
     INTERNAL_ERROR_CODE, INTERNAL_ERROR_MSG, INVALID_PARAMS_CODE, INVALID_PARAMS_MSG,
+    INVALID_REQUEST_CODE,
 };
 
 pub fn invalid_params(details: impl AsRef<str>) -> ErrorObjectOwned {
@@ -18,3 +19,27 @@ pub fn internal_error(details: impl AsRef<str>) -> ErrorObjectOwned {
         Some(details.as_ref()),
     )
 }
+
+pub fn unauthorized() -> ErrorObjectOwned {
+    ErrorObjectOwned::owned(INVALID_REQUEST_CODE, "unauthorized", Some("unauthorized"))
+}
+
+pub fn method_not_allowed(method: &str) -> ErrorObjectOwned {
+    ErrorObjectOwned::owned(
+        INVALID_REQUEST_CODE,
+        "unauthorized",
+        Some(format!("method not allowed for this API key: {method}")),
+    )
+}
+
+pub fn invalid_token(reason: &str) -> ErrorObjectOwned {
+    ErrorObjectOwned::owned(INVALID_REQUEST_CODE, "invalid token", Some(reason))
+}
+
+pub fn websocket_only() -> ErrorObjectOwned {
+    ErrorObjectOwned::owned(
+        INVALID_REQUEST_CODE,
+        "websocket only",
+        Some("only available on WebSocket connections"),
+    )
+}
@@ -0,0 +1,100 @@
+use std::str::FromStr;
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use jsonwebtoken::Algorithm;
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters, Jwk,
+    KeyAlgorithm, OctetKeyPairParameters, PublicKeyUse, RSAKeyParameters,
+};
+use simple_asn1::ASN1Block;
+
+/// Extract the raw key from a PEM encoded `SubjectPublicKeyInfo`,
+/// PKCS#1 RSA public keys are returned as is.
+fn public_key_bytes(public_key_pem: &str) -> anyhow::Result<Vec<u8>> {
+    let pem = pem::parse(public_key_pem)?;
+    if pem.tag() == "RSA PUBLIC KEY" {
+        return Ok(pem.into_contents());
+    }
+
+    match simple_asn1::from_der(pem.contents())?.as_slice() {
+        [ASN1Block::Sequence(_, spki)] => match spki.as_slice() {
+            [ASN1Block::Sequence(_, _), ASN1Block::BitString(_, _, key)] => Ok(key.clone()),
+            _ => anyhow::bail!("invalid SubjectPublicKeyInfo"),
+        },
+        _ => anyhow::bail!("invalid SubjectPublicKeyInfo"),
+    }
+}
+
+/// Extract the modulus and exponent of a PKCS#1 `RSAPublicKey`
+fn rsa_components(key: &[u8]) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+    match simple_asn1::from_der(key)?.as_slice() {
+        [ASN1Block::Sequence(_, components)] => match components.as_slice() {
+            [ASN1Block::Integer(_, n), ASN1Block::Integer(_, e)] => {
+                Ok((n.to_bytes_be().1, e.to_bytes_be().1))
+            }
+            _ => anyhow::bail!("invalid RSA public key"),
+        },
+        _ => anyhow::bail!("invalid RSA public key"),
+    }
+}
+
+/// Build the JWK of an asymmetric signing key from its PEM encoded public key
+pub fn public_jwk(kid: &str, algorithm: Algorithm, public_key_pem: &str) -> anyhow::Result<Jwk> {
+    let key = public_key_bytes(public_key_pem)?;
+
+    let params = match algorithm {
+        Algorithm::ES256 | Algorithm::ES384 => {
+            let (curve, size) = match algorithm {
+                Algorithm::ES256 => (EllipticCurve::P256, 32),
+                _ => (EllipticCurve::P384, 48),
+            };
+            // uncompressed point: 0x04 || x || y
+            if key.len() != 1 + 2 * size || key[0] != 0x04 {
+                anyhow::bail!("invalid EC public key for {algorithm:?}");
+            }
+            AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+                curve,
+                x: URL_SAFE_NO_PAD.encode(&key[1..1 + size]),
+                y: URL_SAFE_NO_PAD.encode(&key[1 + size..]),
+                ..Default::default()
+            })
+        }
+        Algorithm::EdDSA => {
+            if key.len() != 32 {
+                anyhow::bail!("invalid Ed25519 public key");
+            }
+            AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                curve: EllipticCurve::Ed25519,
+                x: URL_SAFE_NO_PAD.encode(key),
+                ..Default::default()
+            })
+        }
+        Algorithm::RS256
+        | Algorithm::RS384
+        | Algorithm::RS512
+        | Algorithm::PS256
+        | Algorithm::PS384
+        | Algorithm::PS512 => {
+            let (n, e) = rsa_components(&key)?;
+            AlgorithmParameters::RSA(RSAKeyParameters {
+                n: URL_SAFE_NO_PAD.encode(n),
+                e: URL_SAFE_NO_PAD.encode(e),
+                ..Default::default()
+            })
+        }
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+            anyhow::bail!("HMAC keys must not be published")
+        }
+    };
+
+    Ok(Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(KeyAlgorithm::from_str(&format!("{algorithm:?}"))?),
+            key_id: Some(kid.to_owned()),
+            ..Default::default()
+        },
+        algorithm: params,
+    })
+}
@@ -1,10 +1,32 @@
 mod access_level;
+mod api_key;
 mod auth_middleware;
+mod eip1271;
+mod eip6492;
 mod error;
+mod jwks;
 mod jwt;
+mod policy;
+mod policy_middleware;
+mod refresh;
+mod request_check;
+mod revocation;
+mod scope_middleware;
+mod session;
+mod session_middleware;
 mod siwe;
+mod ws_auth;
 
 pub use access_level::AccessLevel;
+pub use api_key::{API_KEY_SIZE, ApiKeyConfig, ApiKeys, hash_api_key};
 pub use auth_middleware::AuthenticationMiddleware;
+pub use error::{internal_error, invalid_params, unauthorized};
 pub use jwt::{JwtSigner, JwtSignerKeyConfig};
-pub use siwe::{SiweAuthRpcImpl, SiweAuthRpcServer};
+pub use policy::{AccessPolicy, AccessRule};
+pub use policy_middleware::AccessPolicyMiddleware;
+pub use revocation::{InMemoryRevocationList, SharedRevocationList};
+pub use scope_middleware::{ApiKeyMethods, ApiKeyScopeMiddleware};
+pub use session::{Session, WsSessions, get_access, get_session};
+pub use session_middleware::SessionMiddleware;
+pub use siwe::{SiweAuthRpcImpl, SiweAuthRpcServer, SiweConfig};
+pub use ws_auth::{WsAuthRpcImpl, WsAuthRpcServer};
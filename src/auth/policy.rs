@@ -0,0 +1,433 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use alloy::primitives::Address;
+use jsonrpsee::types::ErrorObjectOwned;
+use serde::Deserialize;
+use serde_json::value::RawValue;
+
+use super::access_level::AccessLevel;
+use super::error::{invalid_params, unauthorized};
+
+/// Access rule of an RPC method
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum AccessRule {
+    /// Anyone, including requests without authorization
+    Public,
+    /// Any authorized request
+    Authenticated,
+    /// Full access, or an API key that is not limited to some addresses
+    Full,
+    /// Admin keys only
+    Admin,
+    /// The address in positional parameter `param` must be accessible
+    SelfAddress { param: usize },
+    /// The sender or receiver of the returned transaction must be accessible
+    SenderOrReceiver,
+    /// The sender of the returned transaction must be accessible
+    Sender,
+    /// Nobody, the method is disabled
+    Deny,
+}
+
+impl AccessRule {
+    /// Check the rule before the call, rules on the result are checked by the method itself
+    pub fn check_request(
+        &self,
+        access: &AccessLevel,
+        params: Option<&RawValue>,
+    ) -> Result<(), ErrorObjectOwned> {
+        let allowed = match self {
+            Self::Public => true,
+            Self::Authenticated | Self::SenderOrReceiver | Self::Sender => {
+                access != &AccessLevel::None
+            }
+            Self::Full => access.has_global_read_access(),
+            Self::Admin => access == &AccessLevel::Full,
+            // leave invalid params to the method
+            Self::SelfAddress { .. } if access == &AccessLevel::Full => true,
+            Self::SelfAddress { param } => access.is_authorized(&address_param(params, *param)?),
+            Self::Deny => false,
+        };
+
+        if !allowed {
+            return Err(unauthorized());
+        }
+        Ok(())
+    }
+
+    /// Whether the rule depends on the sender or receiver of the result
+    pub fn checks_result(&self) -> bool {
+        matches!(self, Self::SenderOrReceiver | Self::Sender)
+    }
+
+    /// Check the sender and receiver of a transaction returned by the method
+    pub fn check_result(
+        &self,
+        access: &AccessLevel,
+        sender: &Address,
+        receiver: Option<&Address>,
+    ) -> bool {
+        match self {
+            Self::SenderOrReceiver => {
+                access.is_authorized(sender) || receiver.is_some_and(|to| access.is_authorized(to))
+            }
+            Self::Sender => access.is_authorized(sender),
+            _ => true,
+        }
+    }
+}
+
+/// Parse the address at position `index` of the JSON-RPC params
+fn address_param(params: Option<&RawValue>, index: usize) -> Result<Address, ErrorObjectOwned> {
+    let invalid = || invalid_params(format!("expected an address at param {index}"));
+
+    let params: Vec<serde_json::Value> = params
+        .and_then(|p| serde_json::from_str(p.get()).ok())
+        .ok_or_else(invalid)?;
+
+    params
+        .get(index)
+        .and_then(|p| serde_json::from_value(p.clone()).ok())
+        .ok_or_else(invalid)
+}
+
+impl FromStr for AccessRule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let rule = match s {
+            "public" => Self::Public,
+            "authenticated" => Self::Authenticated,
+            "full" => Self::Full,
+            "admin" => Self::Admin,
+            "self" => Self::SelfAddress { param: 0 },
+            "sender_or_receiver" => Self::SenderOrReceiver,
+            "sender" => Self::Sender,
+            "deny" => Self::Deny,
+            _ => {
+                let param = s
+                    .strip_prefix("self(param=")
+                    .and_then(|p| p.strip_suffix(')'))
+                    .and_then(|p| p.trim().parse().ok())
+                    .ok_or_else(|| anyhow::anyhow!("unknown access rule: {s}"))?;
+                Self::SelfAddress { param }
+            }
+        };
+        Ok(rule)
+    }
+}
+
+impl TryFrom<String> for AccessRule {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> anyhow::Result<Self> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for AccessRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Public => write!(f, "public"),
+            Self::Authenticated => write!(f, "authenticated"),
+            Self::Full => write!(f, "full"),
+            Self::Admin => write!(f, "admin"),
+            Self::SelfAddress { param } => write!(f, "self(param={param})"),
+            Self::SenderOrReceiver => write!(f, "sender_or_receiver"),
+            Self::Sender => write!(f, "sender"),
+            Self::Deny => write!(f, "deny"),
+        }
+    }
+}
+
+/// Built-in access rules of all RPC methods
+const DEFAULT_RULES: &[(&str, &str)] = &[
+    ("siwe_getNonce", "public"),
+    ("siwe_signIn", "public"),
+    ("siwe_signInWithRefresh", "public"),
+    ("siwe_refresh", "public"),
+    ("siwe_getJwks", "public"),
+    ("siwe_signOut", "public"),
+    ("ws_authenticate", "public"),
+    ("eth_blockNumber", "public"),
+    ("eth_chainId", "public"),
+    ("eth_gasPrice", "public"),
+    ("eth_maxPriorityFeePerGas", "public"),
+    ("eth_feeHistory", "public"),
+    ("eth_getStorageAt", "full"),
+    ("eth_getCode", "full"),
+    ("eth_getBalance", "self(param=0)"),
+    ("eth_getTransactionCount", "self(param=0)"),
+    ("eth_getTransactionByHash", "sender_or_receiver"),
+    ("eth_getTransactionReceipt", "sender_or_receiver"),
+    // contract deployment and whitelisting are checked for basic access on top of this
+    ("eth_sendRawTransaction", "authenticated"),
+    // without full access, only from the caller's address to allowlisted functions
+    ("eth_call", "authenticated"),
+    ("eth_estimateGas", "authenticated"),
+    // without full access, blocks only list the caller's transactions
+    ("eth_getBlockByHash", "authenticated"),
+    ("eth_getBlockByNumber", "authenticated"),
+    // without full access, the filter must be limited to the caller's address
+    ("eth_getLogs", "authenticated"),
+    ("scroll_getL1MessagesInBlock", "full"),
+    ("scroll_withdrawalsByTransaction", "sender"),
+    ("scroll_withdrawalByMessageHash", "sender"),
+    ("scroll_withdrawalsByAddress", "self(param=0)"),
+    // only served when the history index is enabled
+    ("scroll_getTransactionsByAddress", "self(param=0)"),
+    // notifications are filtered by the access level of the subscriber
+    ("eth_subscribe", "authenticated"),
+    // subscription ids are only valid on the connection that created them
+    ("eth_unsubscribe", "public"),
+];
+
+/// Methods whose handler checks the sender or receiver of the result, the rules
+/// `sender_or_receiver` and `sender` would allow any authorized request on other methods
+const RESULT_CHECKED_METHODS: &[&str] = &[
+    "eth_getTransactionByHash",
+    "eth_getTransactionReceipt",
+    "scroll_withdrawalsByTransaction",
+    "scroll_withdrawalByMessageHash",
+];
+
+/// Overrides of the built-in rules, loaded from the policy file
+#[derive(Debug, Default, Deserialize)]
+struct PolicyFile {
+    #[serde(default)]
+    methods: HashMap<String, AccessRule>,
+}
+
+/// Access rules of all RPC methods
+#[derive(Debug)]
+pub struct AccessPolicy {
+    rules: HashMap<String, AccessRule>,
+}
+
+impl Default for AccessPolicy {
+    fn default() -> Self {
+        let rules = DEFAULT_RULES
+            .iter()
+            .map(|(method, rule)| (method.to_string(), rule.parse().expect("valid rule")))
+            .collect();
+        Self { rules }
+    }
+}
+
+impl AccessPolicy {
+    /// Add rules of methods without a built-in rule, e.g. pass-through methods
+    pub fn with_methods<'a>(
+        mut self,
+        methods: impl IntoIterator<Item = (&'a str, AccessRule)>,
+    ) -> anyhow::Result<Self> {
+        for (method, rule) in methods {
+            if self.rules.insert(method.to_owned(), rule).is_some() {
+                anyhow::bail!("access policy: method {method} already has a rule");
+            }
+        }
+        Ok(self)
+    }
+
+    /// Apply the rules of the policy file at `path` if set
+    pub fn with_file(self, path: Option<&str>) -> anyhow::Result<Self> {
+        let Some(path) = path else {
+            return Ok(self);
+        };
+
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("failed to read access policy {path}: {e}"))?;
+        self.with_overrides(&content)
+    }
+
+    /// Apply the rules of a TOML policy, methods without a rule are rejected
+    pub fn with_overrides(mut self, toml: &str) -> anyhow::Result<Self> {
+        let file: PolicyFile = toml::from_str(toml)?;
+
+        for (method, rule) in file.methods {
+            if rule.checks_result() && !RESULT_CHECKED_METHODS.contains(&method.as_str()) {
+                anyhow::bail!(
+                    "access policy: rule {rule} is not supported by method {method}, \
+                     it does not check its result"
+                );
+            }
+            match self.rules.get_mut(&method) {
+                Some(existing) => *existing = rule,
+                None => anyhow::bail!("access policy: unknown method {method}"),
+            }
+        }
+        Ok(self)
+    }
+
+    /// The rule of `method`, `None` if the method is unknown
+    pub fn rule(&self, method: &str) -> Option<AccessRule> {
+        self.rules.get(method).copied()
+    }
+
+    /// Fail if one of the served methods has no rule
+    pub fn check_methods<'a>(
+        &self,
+        methods: impl IntoIterator<Item = &'a str>,
+    ) -> anyhow::Result<()> {
+        for method in methods {
+            if !self.rules.contains_key(method) {
+                anyhow::bail!("access policy: no rule for method {method}");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(json: &str) -> Box<RawValue> {
+        RawValue::from_string(json.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        for rule in [
+            "public",
+            "authenticated",
+            "full",
+            "admin",
+            "self(param=1)",
+            "sender_or_receiver",
+            "sender",
+            "deny",
+        ] {
+            assert_eq!(rule.parse::<AccessRule>().unwrap().to_string(), rule);
+        }
+        assert_eq!(
+            "self".parse::<AccessRule>().unwrap(),
+            AccessRule::SelfAddress { param: 0 }
+        );
+        assert!("self(param=x)".parse::<AccessRule>().is_err());
+        assert!("everyone".parse::<AccessRule>().is_err());
+    }
+
+    #[test]
+    fn test_check_request() {
+        let user = Address::repeat_byte(0x11);
+        let basic = AccessLevel::Basic(user);
+        let own = params(&format!(r#"["{user}", "latest"]"#));
+        let other = params(&format!(r#"["{}", "latest"]"#, Address::ZERO));
+
+        let rule = AccessRule::SelfAddress { param: 0 };
+        assert!(rule.check_request(&basic, Some(&own)).is_ok());
+        assert!(rule.check_request(&basic, Some(&other)).is_err());
+        assert!(rule.check_request(&AccessLevel::None, Some(&own)).is_err());
+        assert!(rule.check_request(&AccessLevel::Full, Some(&other)).is_ok());
+        assert!(rule.check_request(&basic, None).is_err());
+        assert!(
+            AccessRule::SelfAddress { param: 2 }
+                .check_request(&basic, Some(&own))
+                .is_err()
+        );
+
+        assert!(
+            AccessRule::Public
+                .check_request(&AccessLevel::None, None)
+                .is_ok()
+        );
+        assert!(
+            AccessRule::Sender
+                .check_request(&AccessLevel::None, None)
+                .is_err()
+        );
+        assert!(AccessRule::Sender.check_request(&basic, None).is_ok());
+        assert!(AccessRule::Full.check_request(&basic, None).is_err());
+        assert!(
+            AccessRule::Admin
+                .check_request(&AccessLevel::Full, None)
+                .is_ok()
+        );
+        assert!(
+            AccessRule::Deny
+                .check_request(&AccessLevel::Full, None)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_check_result() {
+        let user = Address::repeat_byte(0x11);
+        let basic = AccessLevel::Basic(user);
+
+        let rule = AccessRule::SenderOrReceiver;
+        assert!(rule.check_result(&basic, &user, None));
+        assert!(rule.check_result(&basic, &Address::ZERO, Some(&user)));
+        assert!(!rule.check_result(&basic, &Address::ZERO, None));
+
+        let rule = AccessRule::Sender;
+        assert!(!rule.check_result(&basic, &Address::ZERO, Some(&user)));
+        assert!(AccessRule::Full.check_result(&basic, &Address::ZERO, None));
+    }
+
+    #[test]
+    fn test_policy_overrides() {
+        let policy = AccessPolicy::default()
+            .with_overrides(
+                r#"
+                [methods]
+                eth_getCode = "public"
+                eth_getBalance = "self(param=0)"
+                eth_sendRawTransaction = "deny"
+                "#,
+            )
+            .unwrap();
+
+        assert_eq!(policy.rule("eth_getCode"), Some(AccessRule::Public));
+        assert_eq!(
+            policy.rule("eth_sendRawTransaction"),
+            Some(AccessRule::Deny)
+        );
+        assert_eq!(policy.rule("eth_getStorageAt"), Some(AccessRule::Full));
+        assert_eq!(policy.rule("eth_unknown"), None);
+
+        let err = AccessPolicy::default()
+            .with_overrides("[methods]\neth_unknown = \"public\"")
+            .unwrap_err();
+        assert!(err.to_string().contains("unknown method"));
+        assert!(
+            AccessPolicy::default()
+                .with_overrides("[methods]\neth_getCode = \"everyone\"")
+                .is_err()
+        );
+
+        // Rules on the result only for methods that check them
+        let policy = AccessPolicy::default()
+            .with_overrides("[methods]\neth_getTransactionReceipt = \"sender\"")
+            .unwrap();
+        assert_eq!(
+            policy.rule("eth_getTransactionReceipt"),
+            Some(AccessRule::Sender)
+        );
+        for method in ["eth_getLogs", "eth_getBlockByNumber"] {
+            let err = AccessPolicy::default()
+                .with_overrides(&format!("[methods]\n{method} = \"sender_or_receiver\""))
+                .unwrap_err();
+            assert!(err.to_string().contains("not supported"));
+        }
+
+        assert!(policy.check_methods(["eth_getCode", "siwe_signIn"]).is_ok());
+        assert!(policy.check_methods(["eth_unknown"]).is_err());
+
+        // Rules of pass-through methods can be overridden as well
+        let policy = AccessPolicy::default()
+            .with_methods([("net_version", AccessRule::Public)])
+            .unwrap()
+            .with_overrides("[methods]\nnet_version = \"authenticated\"")
+            .unwrap();
+        assert_eq!(policy.rule("net_version"), Some(AccessRule::Authenticated));
+        assert!(
+            AccessPolicy::default()
+                .with_methods([("eth_getCode", AccessRule::Public)])
+                .is_err()
+        );
+    }
+}
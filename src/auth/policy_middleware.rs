@@ -0,0 +1,33 @@
+use std::sync::Arc;
+
+use jsonrpsee::types::{ErrorObjectOwned, Extensions};
+use serde_json::value::RawValue;
+
+use super::access_level::AccessLevel;
+use super::policy::AccessPolicy;
+use super::request_check::{RequestCheck, RequestCheckMiddleware};
+
+/// Rejects RPC calls that the access policy does not allow
+pub type AccessPolicyMiddleware<S> = RequestCheckMiddleware<S, Arc<AccessPolicy>>;
+
+impl RequestCheck for AccessPolicy {
+    /// Check the access policy of `method` before the call
+    fn check(
+        &self,
+        extensions: &Extensions,
+        method: &str,
+        params: Option<&RawValue>,
+    ) -> Result<(), ErrorObjectOwned> {
+        // unknown methods are answered with "method not found" by the server
+        let Some(rule) = self.rule(method) else {
+            return Ok(());
+        };
+
+        let access = extensions
+            .get::<AccessLevel>()
+            .unwrap_or(&AccessLevel::None);
+        rule.check_request(access, params).inspect_err(|_| {
+            debug!("{method} rejected by access rule {rule} (with access = {access:?})");
+        })
+    }
+}
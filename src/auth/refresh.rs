@@ -0,0 +1,149 @@
+use alloy::primitives::Address;
+use moka::future::Cache;
+use rand::distr::{Alphanumeric, SampleString};
+use std::time::Duration;
+
+const REFRESH_TOKEN_SIZE: usize = 64;
+
+const MAX_REFRESH_TOKENS: u64 = 100_000;
+
+/// Reasons for rejecting a refresh token
+#[derive(Debug, PartialEq)]
+pub enum RefreshTokenError {
+    /// Unknown or expired token
+    Invalid,
+    /// The token was already used, its whole family is revoked
+    Reused,
+}
+
+#[derive(Clone)]
+struct RefreshTokenEntry {
+    address: Address,
+    // All tokens rotated from the same sign-in share a family
+    family: String,
+}
+
+/// In-memory store of single-use, rotating refresh tokens
+pub struct RefreshTokenStore {
+    // Active refresh tokens
+    tokens: Cache<String, RefreshTokenEntry>,
+    // Refresh tokens that were already exchanged (token -> family), kept to detect reuse
+    used: Cache<String, String>,
+    // Families revoked after a reuse was detected
+    revoked_families: Cache<String, ()>,
+}
+
+impl RefreshTokenStore {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            tokens: Cache::builder()
+                .time_to_live(ttl)
+                .max_capacity(MAX_REFRESH_TOKENS)
+                .build(),
+            used: Cache::builder()
+                .time_to_live(ttl)
+                .max_capacity(MAX_REFRESH_TOKENS)
+                .build(),
+            revoked_families: Cache::builder()
+                .time_to_live(ttl)
+                .max_capacity(MAX_REFRESH_TOKENS)
+                .build(),
+        }
+    }
+
+    /// Issue a new refresh token, starting a new family unless `family` is given
+    pub async fn issue(&self, address: Address, family: Option<String>) -> String {
+        let token = Alphanumeric.sample_string(&mut rand::rng(), REFRESH_TOKEN_SIZE);
+        let family = family
+            .unwrap_or_else(|| Alphanumeric.sample_string(&mut rand::rng(), REFRESH_TOKEN_SIZE));
+
+        self.tokens
+            .insert(token.clone(), RefreshTokenEntry { address, family })
+            .await;
+        token
+    }
+
+    /// Consume a refresh token, returning its address and family
+    pub async fn consume(&self, token: &str) -> Result<(Address, String), RefreshTokenError> {
+        if let Some(entry) = self.tokens.remove(token).await {
+            self.used
+                .insert(token.to_owned(), entry.family.clone())
+                .await;
+
+            if self.revoked_families.contains_key(&entry.family) {
+                return Err(RefreshTokenError::Reused);
+            }
+            return Ok((entry.address, entry.family));
+        }
+
+        // A rotated token was presented again, it might have been stolen,
+        // so revoke every token of the family.
+        if let Some(family) = self.used.get(token).await {
+            warn!("Refresh token reuse detected, revoking token family");
+            self.revoked_families.insert(family, ()).await;
+            return Err(RefreshTokenError::Reused);
+        }
+
+        Err(RefreshTokenError::Invalid)
+    }
+
+    /// Revoke the family of a refresh token, e.g. on sign-out
+    pub async fn revoke(&self, token: &str) -> Result<(), RefreshTokenError> {
+        let (address, family) = self.consume(token).await?;
+        debug!("Revoking refresh token family of {address}");
+        self.revoked_families.insert(family, ()).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_refresh_token_rotation() {
+        let store = RefreshTokenStore::new(Duration::from_secs(60));
+        let address = Address::repeat_byte(0x11);
+
+        // Unknown token
+        assert_eq!(
+            store.consume("unknown").await.unwrap_err(),
+            RefreshTokenError::Invalid
+        );
+
+        // Rotate once
+        let token1 = store.issue(address, None).await;
+        let (addr, family) = store.consume(&token1).await.unwrap();
+        assert_eq!(addr, address);
+        let token2 = store.issue(addr, Some(family.clone())).await;
+
+        // Rotate again
+        let (_, family2) = store.consume(&token2).await.unwrap();
+        assert_eq!(family2, family);
+        let token3 = store.issue(addr, Some(family)).await;
+
+        // Another sign-in starts an independent family
+        let other = store.issue(address, None).await;
+
+        // Reusing token1 revokes the family, including the latest token3
+        assert_eq!(
+            store.consume(&token1).await.unwrap_err(),
+            RefreshTokenError::Reused
+        );
+        assert_eq!(
+            store.consume(&token3).await.unwrap_err(),
+            RefreshTokenError::Reused
+        );
+
+        // The other family is unaffected
+        let (_, other_family) = store.consume(&other).await.unwrap();
+        let other = store.issue(address, Some(other_family)).await;
+
+        // Revoking the latest token rejects it afterwards
+        store.revoke(&other).await.unwrap();
+        assert_eq!(
+            store.consume(&other).await.unwrap_err(),
+            RefreshTokenError::Reused
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+use futures_util::future::{Either, ready};
+use jsonrpsee::core::middleware::{Batch, BatchEntry, BatchEntryErr, Notification, RpcServiceT};
+use jsonrpsee::core::server::MethodResponse;
+use jsonrpsee::types::{ErrorObjectOwned, Extensions, Request};
+use serde_json::value::RawValue;
+
+/// A check of each RPC call before it reaches the methods
+pub trait RequestCheck {
+    fn check(
+        &self,
+        extensions: &Extensions,
+        method: &str,
+        params: Option<&RawValue>,
+    ) -> Result<(), ErrorObjectOwned>;
+}
+
+impl<C: RequestCheck> RequestCheck for Arc<C> {
+    fn check(
+        &self,
+        extensions: &Extensions,
+        method: &str,
+        params: Option<&RawValue>,
+    ) -> Result<(), ErrorObjectOwned> {
+        self.as_ref().check(extensions, method, params)
+    }
+}
+
+/// Rejects the calls, batch entries and notifications that fail a `RequestCheck`.
+/// Rejected calls are answered with the error, rejected notifications are dropped.
+#[derive(Clone)]
+pub struct RequestCheckMiddleware<S, C> {
+    service: S,
+    check: C,
+}
+
+impl<S, C> RequestCheckMiddleware<S, C> {
+    pub fn new(service: S, check: C) -> Self {
+        Self { service, check }
+    }
+}
+
+impl<S, C> RpcServiceT for RequestCheckMiddleware<S, C>
+where
+    S: RpcServiceT<
+            MethodResponse = MethodResponse,
+            NotificationResponse = MethodResponse,
+            BatchResponse = MethodResponse,
+        > + Send
+        + Sync
+        + Clone
+        + 'static,
+    C: RequestCheck + Send + Sync + Clone + 'static,
+{
+    type MethodResponse = S::MethodResponse;
+    type NotificationResponse = S::NotificationResponse;
+    type BatchResponse = S::BatchResponse;
+
+    fn call<'a>(&self, req: Request<'a>) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+        let checked = self
+            .check
+            .check(req.extensions(), &req.method, req.params.as_deref());
+        if let Err(err) = checked {
+            return Either::Left(ready(MethodResponse::error(req.id, err)));
+        }
+        Either::Right(self.service.call(req))
+    }
+
+    fn batch<'a>(&self, batch: Batch<'a>) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+        // the inner service does not go through `call` for batch entries, check them here
+        let entries = batch
+            .into_iter()
+            .filter_map(|entry| match entry {
+                Ok(BatchEntry::Call(req)) => {
+                    let checked =
+                        self.check
+                            .check(req.extensions(), &req.method, req.params.as_deref());
+                    match checked {
+                        Ok(()) => Some(Ok(BatchEntry::Call(req))),
+                        Err(err) => Some(Err(BatchEntryErr::new(req.id, err))),
+                    }
+                }
+                // notifications get no response, just drop them
+                Ok(BatchEntry::Notification(n)) => self
+                    .check
+                    .check(n.extensions(), &n.method, n.params.as_deref())
+                    .ok()
+                    .map(|()| Ok(BatchEntry::Notification(n))),
+                entry => Some(entry),
+            })
+            .collect();
+
+        self.service.batch(Batch::from(entries))
+    }
+
+    fn notification<'a>(
+        &self,
+        n: Notification<'a>,
+    ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+        let checked = self
+            .check
+            .check(n.extensions(), &n.method, n.params.as_deref());
+        if checked.is_err() {
+            return Either::Left(ready(MethodResponse::notification()));
+        }
+        Either::Right(self.service.notification(n))
+    }
+}
@@ -0,0 +1,117 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use jsonrpsee::core::async_trait;
+use moka::Expiry;
+use moka::future::Cache;
+
+// Tokens are accepted up to this long after `exp`, see `jsonwebtoken::Validation::leeway`.
+const EXPIRY_LEEWAY_SECS: u64 = 60;
+
+/// Storage of revoked JWT ids, entries are only needed until the token expires.
+///
+/// The in-memory implementation is local to a single proxy instance,
+/// deployments with multiple instances can plug in a shared store.
+#[async_trait]
+pub trait RevocationList: Send + Sync {
+    /// Revoke the token with id `jti`, expiring at unix time `exp`
+    async fn revoke(&self, jti: String, exp: usize);
+
+    /// Check whether the token with id `jti` was revoked
+    async fn is_revoked(&self, jti: &str) -> bool;
+}
+
+pub type SharedRevocationList = Arc<dyn RevocationList>;
+
+/// Expire each entry once its token is expired
+struct TokenExpiry;
+
+impl Expiry<String, usize> for TokenExpiry {
+    fn expire_after_create(
+        &self,
+        _jti: &String,
+        exp: &usize,
+        _created_at: Instant,
+    ) -> Option<Duration> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let remaining = (*exp as u64).saturating_sub(now) + EXPIRY_LEEWAY_SECS;
+        Some(Duration::from_secs(remaining))
+    }
+}
+
+/// In-memory revocation list. It has no capacity limit: evicting a revoked token before it
+/// expires would make it valid again, so entries are only removed once their token expires.
+pub struct InMemoryRevocationList {
+    revoked: Cache<String, usize>,
+}
+
+impl InMemoryRevocationList {
+    pub fn new() -> Self {
+        Self {
+            revoked: Cache::builder().expire_after(TokenExpiry).build(),
+        }
+    }
+}
+
+impl Default for InMemoryRevocationList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl RevocationList for InMemoryRevocationList {
+    async fn revoke(&self, jti: String, exp: usize) {
+        self.revoked.insert(jti, exp).await;
+    }
+
+    async fn is_revoked(&self, jti: &str) -> bool {
+        self.revoked.contains_key(jti)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_expiry() {
+        let now = chrono::Utc::now().timestamp() as usize;
+
+        let ttl = TokenExpiry.expire_after_create(&"a".to_string(), &(now + 3600), Instant::now());
+        let ttl = ttl.unwrap().as_secs();
+        assert!(ttl > 3600 && ttl <= 3600 + EXPIRY_LEEWAY_SECS);
+
+        // Already expired tokens are kept for the leeway only
+        let ttl = TokenExpiry.expire_after_create(&"b".to_string(), &(now - 3600), Instant::now());
+        assert_eq!(ttl, Some(Duration::from_secs(EXPIRY_LEEWAY_SECS)));
+    }
+
+    #[tokio::test]
+    async fn test_revoke() {
+        let list = InMemoryRevocationList::new();
+        let exp = chrono::Utc::now().timestamp() as usize + 3600;
+
+        assert!(!list.is_revoked("jti-1").await);
+        list.revoke("jti-1".to_string(), exp).await;
+        assert!(list.is_revoked("jti-1").await);
+        assert!(!list.is_revoked("jti-2").await);
+    }
+
+    #[tokio::test]
+    async fn test_revoked_tokens_not_evicted() {
+        let list = InMemoryRevocationList::new();
+        let exp = chrono::Utc::now().timestamp() as usize + 3600;
+
+        // Signing out many tokens, e.g. to flood the list, doesn't evict earlier entries
+        list.revoke("stolen".to_string(), exp).await;
+        for i in 0..100_000 {
+            list.revoke(format!("jti-{i}"), exp).await;
+        }
+        list.revoked.run_pending_tasks().await;
+        assert!(list.is_revoked("stolen").await);
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use jsonrpsee::types::{ErrorObjectOwned, Extensions};
+use serde_json::value::RawValue;
+
+use super::access_level::AccessLevel;
+use super::error::method_not_allowed;
+use super::request_check::{RequestCheck, RequestCheckMiddleware};
+
+/// Rejects RPC methods that the API key of the request is not scoped for
+pub type ApiKeyScopeMiddleware<S> = RequestCheckMiddleware<S, ApiKeyMethods>;
+
+/// Built-in methods that change state, not available to read-only API keys
+const WRITE_METHODS: &[&str] = &["eth_sendRawTransaction"];
+
+/// The methods API keys are scoped to
+#[derive(Clone)]
+pub struct ApiKeyMethods {
+    // Methods read-only keys may not call
+    write_methods: Arc<HashSet<String>>,
+}
+
+impl ApiKeyMethods {
+    /// `write_methods` are the pass-through methods that may change state,
+    /// in addition to the built-in ones
+    pub fn new<'a>(write_methods: impl IntoIterator<Item = &'a str>) -> Self {
+        let write_methods = WRITE_METHODS
+            .iter()
+            .copied()
+            .chain(write_methods)
+            .map(String::from)
+            .collect();
+        Self {
+            write_methods: Arc::new(write_methods),
+        }
+    }
+}
+
+impl RequestCheck for ApiKeyMethods {
+    /// Check whether the access level of a request allows calling `method`
+    fn check(
+        &self,
+        extensions: &Extensions,
+        method: &str,
+        _params: Option<&RawValue>,
+    ) -> Result<(), ErrorObjectOwned> {
+        let write = self.write_methods.contains(method);
+        match extensions.get::<AccessLevel>() {
+            Some(access) if !access.allows_method(method, write) => {
+                if let AccessLevel::Scoped(scope) = access {
+                    debug!("API key '{}' is not allowed to call {method}", scope.name);
+                }
+                Err(method_not_allowed(method))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{ApiKeyConfig, ApiKeys};
+
+    #[test]
+    fn test_read_only_api_keys() {
+        let keys = ApiKeys::from_config(
+            &[],
+            &[ApiKeyConfig {
+                name: "partner".to_string(),
+                key: "read-only".to_string(),
+                methods: None,
+                addresses: None,
+                read_only: true,
+                expires_at: None,
+                enabled: true,
+            }],
+        )
+        .unwrap();
+        let mut ext = Extensions::new();
+        ext.insert(keys.authenticate("read-only", 0).unwrap().unwrap());
+
+        let methods = ApiKeyMethods::new(["debug_setHead"]);
+        assert!(methods.check(&ext, "eth_getBalance", None).is_ok());
+        assert!(methods.check(&ext, "net_version", None).is_ok());
+        assert!(methods.check(&ext, "eth_sendRawTransaction", None).is_err());
+        assert!(methods.check(&ext, "debug_setHead", None).is_err());
+
+        // Other access levels are not limited
+        ext.insert(AccessLevel::Full);
+        assert!(methods.check(&ext, "debug_setHead", None).is_ok());
+    }
+}
@@ -0,0 +1,238 @@
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use jsonrpsee::core::server::ConnectionId;
+use jsonrpsee::types::Extensions;
+use moka::Expiry;
+use moka::sync::Cache;
+
+use super::access_level::AccessLevel;
+
+// Sessions without expiry (API keys) are dropped after this long without requests
+const SESSION_IDLE_SECS: u64 = 24 * 3600;
+
+const MAX_SESSIONS: u64 = 100_000;
+
+/// Access granted by a token, and when it ends
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session {
+    pub access: AccessLevel,
+    /// Unix time the token expires, `None` for tokens without expiry
+    pub expires_at: Option<i64>,
+    /// Id (`jti`) of the JWT, to check whether it was revoked
+    pub token_id: Option<String>,
+}
+
+impl Session {
+    /// Session of requests without authorization
+    pub fn anonymous() -> Self {
+        Self {
+            access: AccessLevel::None,
+            expires_at: None,
+            token_id: None,
+        }
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|exp| exp <= now)
+    }
+}
+
+/// Expiry of the token of a request, stored in the extensions next to its `AccessLevel`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AccessExpiry(pub i64);
+
+/// Id of the JWT of a request, stored in the extensions next to its `AccessLevel`
+#[derive(Clone, Debug, PartialEq)]
+pub struct TokenId(pub String);
+
+/// Marks requests received on a WebSocket connection
+#[derive(Clone, Copy, Debug)]
+pub struct WebSocketConnection;
+
+/// Expire each session once its token is expired
+struct SessionExpiry;
+
+impl Expiry<ConnectionId, Session> for SessionExpiry {
+    fn expire_after_create(
+        &self,
+        _conn: &ConnectionId,
+        session: &Session,
+        _created_at: Instant,
+    ) -> Option<Duration> {
+        let exp = session.expires_at?;
+        let remaining = exp.saturating_sub(Utc::now().timestamp()).max(0) as u64;
+        Some(Duration::from_secs(remaining))
+    }
+}
+
+/// Sessions of WebSocket connections authenticated with `ws_authenticate`,
+/// they replace the authorization header of the connection upgrade.
+#[derive(Clone)]
+pub struct WsSessions {
+    sessions: Cache<ConnectionId, Session>,
+}
+
+impl WsSessions {
+    pub fn new() -> Self {
+        Self {
+            sessions: Cache::builder()
+                .expire_after(SessionExpiry)
+                .time_to_idle(Duration::from_secs(SESSION_IDLE_SECS))
+                .max_capacity(MAX_SESSIONS)
+                .build(),
+        }
+    }
+
+    pub fn insert(&self, conn: ConnectionId, session: Session) {
+        self.sessions.insert(conn, session);
+    }
+
+    /// Drop the session of a closed connection
+    pub fn remove(&self, conn: &ConnectionId) {
+        self.sessions.invalidate(conn);
+    }
+
+    /// The session of a connection, if it is not expired
+    pub fn get(&self, conn: &ConnectionId, now: i64) -> Option<Session> {
+        self.sessions.get(conn).filter(|s| !s.is_expired(now))
+    }
+
+    /// Set the access level of a request from the session of its WebSocket connection,
+    /// requests with an expired token get `AccessLevel::None`.
+    pub fn apply(&self, extensions: &mut Extensions, now: i64) {
+        if extensions.get::<WebSocketConnection>().is_some()
+            && let Some(conn) = extensions.get::<ConnectionId>().copied()
+            && let Some(session) = self.get(&conn, now)
+        {
+            set_session(extensions, session);
+        }
+
+        if extensions
+            .get::<AccessExpiry>()
+            .is_some_and(|exp| exp.0 <= now)
+        {
+            set_session(extensions, Session::anonymous());
+        }
+    }
+}
+
+impl Default for WsSessions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The access level of a request, from its extensions
+pub fn get_access(extensions: &Extensions) -> &AccessLevel {
+    extensions
+        .get::<AccessLevel>()
+        .unwrap_or(&AccessLevel::None)
+}
+
+/// The session of a request, from its extensions
+pub fn get_session(extensions: &Extensions) -> Session {
+    Session {
+        access: extensions
+            .get::<AccessLevel>()
+            .cloned()
+            .unwrap_or(AccessLevel::None),
+        expires_at: extensions.get::<AccessExpiry>().map(|exp| exp.0),
+        token_id: extensions.get::<TokenId>().map(|id| id.0.clone()),
+    }
+}
+
+/// Store the access level, expiry and token id of a session in the extensions of a request
+pub fn set_session(extensions: &mut Extensions, session: Session) {
+    extensions.insert(session.access);
+    match session.expires_at {
+        Some(exp) => {
+            extensions.insert(AccessExpiry(exp));
+        }
+        None => {
+            extensions.remove::<AccessExpiry>();
+        }
+    }
+    match session.token_id {
+        Some(id) => {
+            extensions.insert(TokenId(id));
+        }
+        None => {
+            extensions.remove::<TokenId>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Address;
+
+    #[test]
+    fn test_apply_session() {
+        let sessions = WsSessions::new();
+        let now = Utc::now().timestamp();
+        let user = AccessLevel::Basic(Address::repeat_byte(0x11));
+
+        // Expired token
+        let mut ext = Extensions::new();
+        set_session(
+            &mut ext,
+            Session {
+                access: user.clone(),
+                expires_at: Some(now - 1),
+                token_id: None,
+            },
+        );
+        sessions.apply(&mut ext, now);
+        assert_eq!(get_session(&ext), Session::anonymous());
+
+        // Valid token
+        let session = Session {
+            access: user.clone(),
+            expires_at: Some(now + 60),
+            token_id: Some("token".to_string()),
+        };
+        let mut ext = Extensions::new();
+        set_session(&mut ext, session.clone());
+        sessions.apply(&mut ext, now);
+        assert_eq!(get_session(&ext), session);
+        sessions.apply(&mut ext, now + 60);
+        assert_eq!(get_session(&ext), Session::anonymous());
+
+        // The session of the connection replaces the header authorization
+        let conn = ConnectionId(1);
+        sessions.insert(
+            conn,
+            Session {
+                access: AccessLevel::Full,
+                expires_at: None,
+                token_id: None,
+            },
+        );
+        let mut ext = Extensions::new();
+        ext.insert(conn);
+        set_session(&mut ext, session.clone());
+        sessions.apply(&mut ext, now);
+        assert_eq!(get_session(&ext), session, "not a WebSocket connection");
+        ext.insert(WebSocketConnection);
+        sessions.apply(&mut ext, now + 120);
+        assert_eq!(get_session(&ext).access, AccessLevel::Full);
+        assert_eq!(get_session(&ext).expires_at, None);
+
+        // Expired sessions are ignored
+        sessions.insert(
+            conn,
+            Session {
+                access: user,
+                expires_at: Some(now + 60),
+                token_id: None,
+            },
+        );
+        assert!(sessions.get(&conn, now).is_some());
+        assert!(sessions.get(&conn, now + 60).is_none());
+
+        sessions.remove(&conn);
+        assert!(sessions.get(&conn, now).is_none());
+    }
+}
@@ -0,0 +1,135 @@
+use std::sync::{Arc, OnceLock};
+
+use chrono::Utc;
+use jsonrpsee::core::middleware::{Batch, Notification, RpcServiceT};
+use jsonrpsee::core::server::ConnectionId;
+use jsonrpsee::types::{Extensions, Request};
+
+use super::session::{WebSocketConnection, WsSessions};
+
+/// Applies WebSocket sessions and token expiry to the access level of each call,
+/// the access level of a WebSocket connection is otherwise fixed at the connection upgrade.
+#[derive(Clone)]
+pub struct SessionMiddleware<S> {
+    service: S,
+    connection: Arc<ConnectionSession>,
+}
+
+impl<S> SessionMiddleware<S> {
+    /// The middleware is built for each connection, and dropped when the connection closes
+    pub fn new(service: S, sessions: WsSessions) -> Self {
+        Self {
+            service,
+            connection: Arc::new(ConnectionSession {
+                sessions,
+                conn: OnceLock::new(),
+            }),
+        }
+    }
+}
+
+/// The session of a WebSocket connection, removed once the connection is closed
+/// so that it doesn't count towards the session limit.
+struct ConnectionSession {
+    sessions: WsSessions,
+    conn: OnceLock<ConnectionId>,
+}
+
+impl ConnectionSession {
+    fn apply(&self, extensions: &mut Extensions, now: i64) {
+        if self.conn.get().is_none()
+            && extensions.get::<WebSocketConnection>().is_some()
+            && let Some(conn) = extensions.get::<ConnectionId>()
+        {
+            let _ = self.conn.set(*conn);
+        }
+        self.sessions.apply(extensions, now);
+    }
+}
+
+impl Drop for ConnectionSession {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.get() {
+            self.sessions.remove(conn);
+        }
+    }
+}
+
+impl<S> RpcServiceT for SessionMiddleware<S>
+where
+    S: RpcServiceT + Send + Sync + Clone + 'static,
+{
+    type MethodResponse = S::MethodResponse;
+    type NotificationResponse = S::NotificationResponse;
+    type BatchResponse = S::BatchResponse;
+
+    fn call<'a>(
+        &self,
+        mut req: Request<'a>,
+    ) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+        self.connection
+            .apply(req.extensions_mut(), Utc::now().timestamp());
+        self.service.call(req)
+    }
+
+    fn batch<'a>(
+        &self,
+        mut batch: Batch<'a>,
+    ) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+        let now = Utc::now().timestamp();
+        for entry in batch.iter_mut().flatten() {
+            self.connection.apply(entry.extensions_mut(), now);
+        }
+        self.connection.apply(batch.extensions_mut(), now);
+        self.service.batch(batch)
+    }
+
+    fn notification<'a>(
+        &self,
+        mut n: Notification<'a>,
+    ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+        self.connection
+            .apply(n.extensions_mut(), Utc::now().timestamp());
+        self.service.notification(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{AccessLevel, Session};
+
+    #[test]
+    fn test_session_removed_on_close() {
+        let sessions = WsSessions::new();
+        let now = Utc::now().timestamp();
+        let conn = ConnectionId(1);
+        let session = Session {
+            access: AccessLevel::Full,
+            expires_at: None,
+            token_id: None,
+        };
+        sessions.insert(conn, session.clone());
+
+        // A HTTP request doesn't own the session of the connection id
+        let connection = ConnectionSession {
+            sessions: sessions.clone(),
+            conn: OnceLock::new(),
+        };
+        let mut ext = Extensions::new();
+        ext.insert(conn);
+        connection.apply(&mut ext, now);
+        drop(connection);
+        assert_eq!(sessions.get(&conn, now), Some(session));
+
+        let connection = ConnectionSession {
+            sessions: sessions.clone(),
+            conn: OnceLock::new(),
+        };
+        ext.insert(WebSocketConnection);
+        connection.apply(&mut ext, now);
+        assert_eq!(ext.get::<AccessLevel>(), Some(&AccessLevel::Full));
+        drop(connection);
+        assert!(sessions.get(&conn, now).is_none());
+    }
+}
@@ -1,27 +1,97 @@
-use alloy::primitives::Bytes;
-use chrono::Utc;
+use alloy::primitives::{Address, B256, Bytes};
+use chrono::{DateTime, Utc};
 use jsonrpsee::core::{RpcResult, async_trait};
+use jsonrpsee::http_client::HttpClient;
 use jsonrpsee::proc_macros::rpc;
+use jsonwebtoken::jwk::JwkSet;
 use moka::future::Cache;
 use rand::distr::{Alphanumeric, SampleString};
-use siwe::{Message, VerificationOpts};
+use serde::{Deserialize, Serialize};
+use siwe::{Message, TimeStamp, VerificationOpts};
 use std::sync::Arc;
 use std::time::Duration;
 
+use super::eip1271::verify_eip1271;
+use super::eip6492::{Eip6492Signature, verify_eip6492};
 use super::error::{internal_error, invalid_params};
-use super::jwt::JwtSigner;
+use super::jwt::{JwtSigner, TokenError};
+use super::refresh::{RefreshTokenError, RefreshTokenStore};
+use super::revocation::SharedRevocationList;
+use crate::metrics::metrics;
 
 type NonceCache = Arc<Cache<String, ()>>;
 
 const NONCE_SIZE: usize = 64;
 
+// Nonces must be used within this time after being issued.
+const NONCE_TTL_SECS: i64 = 300;
+
+/// Configuration for SIWE message verification, unset fields accept any value
+#[derive(Debug, Clone, Deserialize)]
+pub struct SiweConfig {
+    /// Accepted values for the message `domain`, e.g. "app.example.com"
+    #[serde(default)]
+    pub domains: Vec<String>,
+    /// Required prefix of the message `uri`, e.g. "https://app.example.com/"
+    #[serde(default)]
+    pub uri_prefix: String,
+    /// Required message `chain_id`, should match the validium chain id
+    #[serde(default)]
+    pub chain_id: Option<u64>,
+    /// Maximum allowed clock difference for `issued_at` and `not_before`
+    #[serde(default = "default_max_clock_skew_secs")]
+    pub max_clock_skew_secs: i64,
+}
+
+impl Default for SiweConfig {
+    fn default() -> Self {
+        Self {
+            domains: Vec::new(),
+            uri_prefix: String::new(),
+            chain_id: None,
+            max_clock_skew_secs: default_max_clock_skew_secs(),
+        }
+    }
+}
+
+fn default_max_clock_skew_secs() -> i64 {
+    60
+}
+
+/// Tokens issued on sign-in and refresh
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SiweTokens {
+    /// Short-lived JWT used as bearer token
+    pub access_token: String,
+    /// Long-lived, single-use token to obtain a new pair via `siwe_refresh`
+    pub refresh_token: String,
+}
+
 #[rpc(server, client, namespace = "siwe")]
 pub trait SiweAuthRpc {
     #[method(name = "getNonce")]
     async fn get_nonce(&self) -> RpcResult<String>;
 
+    /// Returns an access token only, see `signInWithRefresh` for a refresh token too
     #[method(name = "signIn")]
     async fn sign_in(&self, message: String, signature: Bytes) -> RpcResult<String>;
+
+    #[method(name = "signInWithRefresh")]
+    async fn sign_in_with_refresh(
+        &self,
+        message: String,
+        signature: Bytes,
+    ) -> RpcResult<SiweTokens>;
+
+    #[method(name = "refresh")]
+    async fn refresh(&self, refresh_token: String) -> RpcResult<SiweTokens>;
+
+    #[method(name = "getJwks")]
+    async fn get_jwks(&self) -> RpcResult<JwkSet>;
+
+    #[method(name = "signOut")]
+    async fn sign_out(&self, access_token: String, refresh_token: Option<String>) -> RpcResult<()>;
 }
 
 pub struct SiweAuthRpcImpl {
@@ -29,57 +99,651 @@ pub struct SiweAuthRpcImpl {
     jwt: JwtSigner,
     // JWT expiration time in seconds, timeout is not exact, there is a 60s leeway by default.
     jwt_expiry_secs: usize,
+    refresh_tokens: RefreshTokenStore,
+    revoked_tokens: SharedRevocationList,
+    config: SiweConfig,
+    // Used for on-chain signature checks of contract wallets.
+    validium_client: HttpClient,
 }
 
 impl SiweAuthRpcImpl {
-    pub fn new(jwt: JwtSigner, jwt_expiry_secs: usize) -> Self {
+    pub fn new(
+        jwt: JwtSigner,
+        jwt_expiry_secs: usize,
+        refresh_token_expiry_secs: usize,
+        revoked_tokens: SharedRevocationList,
+        config: SiweConfig,
+        validium_client: HttpClient,
+    ) -> Self {
         let cache: NonceCache = Arc::new(
             Cache::builder()
-                .time_to_live(Duration::from_secs(300))
+                .time_to_live(Duration::from_secs(NONCE_TTL_SECS as u64))
                 .max_capacity(10_000)
                 .build(),
         );
 
+        let refresh_tokens =
+            RefreshTokenStore::new(Duration::from_secs(refresh_token_expiry_secs as u64));
+
         Self {
             cache,
             jwt,
             jwt_expiry_secs,
+            refresh_tokens,
+            revoked_tokens,
+            config,
+            validium_client,
+        }
+    }
+
+    /// Issue an access token for `address`
+    fn issue_access_token(&self, address: Address) -> RpcResult<String> {
+        let exp = (Utc::now().timestamp() as usize) + self.jwt_expiry_secs;
+        match self.jwt.create_token(address, exp) {
+            Ok(token) => Ok(token),
+            Err(_) => Err(internal_error("unable to issue token")),
+        }
+    }
+
+    /// Issue an access token and a refresh token, `family` is set when rotating a refresh token
+    async fn issue_tokens(
+        &self,
+        address: Address,
+        family: Option<String>,
+    ) -> RpcResult<SiweTokens> {
+        let access_token = self.issue_access_token(address)?;
+        let refresh_token = self.refresh_tokens.issue(address, family).await;
+
+        Ok(SiweTokens {
+            access_token,
+            refresh_token,
+        })
+    }
+
+    /// Verify a signed SIWE message, returns its address
+    async fn verify_sign_in(&self, message: String, signature: Bytes) -> RpcResult<Address> {
+        let message = match message.parse::<Message>() {
+            Ok(m) => m,
+            Err(e) => return Err(invalid_params(format!("invalid message: {e}"))),
+        };
+
+        let nonce = self.cache.remove(&message.nonce).await;
+        metrics().set_siwe_nonces(self.cache.entry_count());
+        if nonce.is_none() {
+            return Err(invalid_params(format!(
+                "invalid message nonce: {}",
+                message.nonce
+            )));
+        }
+
+        verify_message_fields(&self.config, &message, Utc::now().timestamp())?;
+
+        self.verify_signature(&message, &signature).await?;
+        Ok(Address::from(message.address))
+    }
+
+    /// Verify the message signature, falling back to EIP-1271 for contract wallets
+    /// and EIP-6492 for smart accounts that are not deployed yet
+    async fn verify_signature(&self, message: &Message, signature: &Bytes) -> RpcResult<()> {
+        let hash = match message.eip191_hash() {
+            Ok(hash) => B256::from(hash),
+            Err(_) => return Err(invalid_params("invalid message")),
+        };
+
+        let wallet = Address::from(message.address);
+
+        let verified = if Eip6492Signature::is_wrapped(signature) {
+            let wrapped = match Eip6492Signature::decode(signature) {
+                Some(wrapped) => wrapped,
+                None => return Err(invalid_params("invalid EIP-6492 signature")),
+            };
+            verify_eip6492(&self.validium_client, wallet, hash, &wrapped).await
+        } else {
+            let opts = VerificationOpts::default();
+            if message.verify(signature, &opts).await.is_ok() {
+                return Ok(());
+            }
+            verify_eip1271(&self.validium_client, wallet, hash, signature).await
+        };
+
+        match verified {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(invalid_params("invalid message or signature")),
+            Err(e) => {
+                warn!("Contract signature check for {wallet} failed: {e}");
+                Err(internal_error("unable to verify contract wallet signature"))
+            }
         }
     }
 }
 
+fn unix_timestamp(ts: &TimeStamp) -> RpcResult<i64> {
+    DateTime::parse_from_rfc3339(&ts.to_string())
+        .map(|t| t.timestamp())
+        .map_err(|e| invalid_params(format!("invalid message timestamp {ts}: {e}")))
+}
+
+/// Check whether `uri` starts with `prefix` on a path, query or fragment boundary
+fn has_uri_prefix(uri: &str, prefix: &str) -> bool {
+    match uri.strip_prefix(prefix) {
+        None => false,
+        Some(rest) => rest.is_empty() || prefix.ends_with('/') || rest.starts_with(['/', '?', '#']),
+    }
+}
+
+/// Verify the message fields against the configuration, the signature is checked separately
+fn verify_message_fields(config: &SiweConfig, message: &Message, now: i64) -> RpcResult<()> {
+    let domain = message.domain.as_str();
+    if !config.domains.is_empty()
+        && !config
+            .domains
+            .iter()
+            .any(|d| d.eq_ignore_ascii_case(domain))
+    {
+        return Err(invalid_params(format!("invalid message domain: {domain}")));
+    }
+
+    let uri = message.uri.as_str();
+    if !config.uri_prefix.is_empty() && !has_uri_prefix(uri, &config.uri_prefix) {
+        return Err(invalid_params(format!("invalid message URI: {uri}")));
+    }
+
+    if let Some(chain_id) = config.chain_id
+        && message.chain_id != chain_id
+    {
+        return Err(invalid_params(format!(
+            "invalid message Chain ID: {}, expected {chain_id}",
+            message.chain_id
+        )));
+    }
+
+    let issued_at = unix_timestamp(&message.issued_at)?;
+    if issued_at > now + config.max_clock_skew_secs {
+        return Err(invalid_params(format!(
+            "invalid message Issued At: {} is in the future",
+            message.issued_at
+        )));
+    }
+    if issued_at < now - NONCE_TTL_SECS - config.max_clock_skew_secs {
+        return Err(invalid_params(format!(
+            "invalid message Issued At: {} is too old",
+            message.issued_at
+        )));
+    }
+
+    if let Some(expiration_time) = &message.expiration_time
+        && unix_timestamp(expiration_time)? <= now
+    {
+        return Err(invalid_params(format!(
+            "invalid message Expiration Time: {expiration_time} has passed"
+        )));
+    }
+
+    if let Some(not_before) = &message.not_before
+        && unix_timestamp(not_before)? > now + config.max_clock_skew_secs
+    {
+        return Err(invalid_params(format!(
+            "invalid message Not Before: {not_before} is in the future"
+        )));
+    }
+
+    Ok(())
+}
+
 #[async_trait]
 impl SiweAuthRpcServer for SiweAuthRpcImpl {
     async fn get_nonce(&self) -> RpcResult<String> {
         let nonce = Alphanumeric.sample_string(&mut rand::rng(), NONCE_SIZE);
         self.cache.insert(nonce.clone(), ()).await;
+        metrics().set_siwe_nonces(self.cache.entry_count());
         Ok(nonce)
     }
 
     async fn sign_in(&self, message: String, signature: Bytes) -> RpcResult<String> {
-        let message = match message.parse::<Message>() {
-            Ok(m) => m,
-            Err(e) => return Err(invalid_params(format!("invalid message: {e}"))),
+        let result = self.verify_sign_in(message, signature).await;
+        metrics().record_sign_in(result.is_ok());
+        self.issue_access_token(result?)
+    }
+
+    async fn sign_in_with_refresh(
+        &self,
+        message: String,
+        signature: Bytes,
+    ) -> RpcResult<SiweTokens> {
+        let result = self.verify_sign_in(message, signature).await;
+        metrics().record_sign_in(result.is_ok());
+        self.issue_tokens(result?, None).await
+    }
+
+    async fn refresh(&self, refresh_token: String) -> RpcResult<SiweTokens> {
+        let (address, family) = match self.refresh_tokens.consume(&refresh_token).await {
+            Ok(entry) => entry,
+            Err(RefreshTokenError::Invalid) => {
+                return Err(invalid_params("invalid refresh token"));
+            }
+            Err(RefreshTokenError::Reused) => {
+                return Err(invalid_params(
+                    "refresh token already used, please sign in again",
+                ));
+            }
         };
 
-        if self.cache.remove(&message.nonce).await.is_none() {
-            return Err(invalid_params(format!(
-                "invalid message nonce: {}",
-                message.nonce
-            )));
+        self.issue_tokens(address, Some(family)).await
+    }
+
+    async fn get_jwks(&self) -> RpcResult<JwkSet> {
+        Ok(self.jwt.jwks())
+    }
+
+    async fn sign_out(&self, access_token: String, refresh_token: Option<String>) -> RpcResult<()> {
+        // the refresh token is revoked on its own, it outlives the access token.
+        // An expired or already rotated refresh token can't be used anyway.
+        if let Some(refresh_token) = refresh_token {
+            let _ = self.refresh_tokens.revoke(&refresh_token).await;
         }
 
-        // TODO: make verification strict
-        let opts = VerificationOpts::default();
+        let claims = match self.jwt.decode_token(&access_token) {
+            Ok(claims) => claims,
+            // an expired access token can't be used anymore, there is nothing to revoke
+            Err(TokenError::Expired) => return Ok(()),
+            Err(_) => return Err(invalid_params("invalid access token")),
+        };
 
-        if let Err(_e) = message.verify(&signature, &opts).await {
-            return Err(invalid_params("invalid message or signature"));
+        // tokens without id can't be revoked, they stay valid until they expire
+        if let Some(jti) = claims.jti {
+            self.revoked_tokens.revoke(jti, claims.exp).await;
         }
 
-        let exp = (Utc::now().timestamp() as usize) + self.jwt_expiry_secs;
-        match self.jwt.create_token(message.address, exp) {
-            Ok(token) => Ok(token),
-            Err(_) => Err(internal_error("unable to issue token")),
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::eip1271::{EIP1271_MAGIC_VALUE, IERC1271};
+    use crate::auth::eip6492::tests::{parse_validator_code, wrap};
+    use crate::auth::revocation::RevocationList;
+    use crate::auth::{InMemoryRevocationList, JwtSignerKeyConfig};
+    use crate::test_utils::mock_server;
+    use alloy::rpc::types::TransactionRequest;
+    use alloy::sol_types::SolCall;
+    use chrono::SecondsFormat;
+    use jsonrpsee::RpcModule;
+    use jsonrpsee::server::ServerHandle;
+
+    const NOW: i64 = 1_760_000_000; // 2025-10-09T08:53:20Z
+
+    fn config() -> SiweConfig {
+        SiweConfig {
+            domains: vec!["app.example.com".to_string()],
+            uri_prefix: "https://app.example.com/".to_string(),
+            chain_id: Some(534352),
+            max_clock_skew_secs: 60,
         }
     }
+
+    fn message(domain: &str, uri: &str, chain_id: u64, times: &str) -> Message {
+        format!(
+            "{domain} wants you to sign in with your Ethereum account:\n\
+             0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2\n\
+             \n\
+             Sign in with Ethereum to the app.\n\
+             \n\
+             URI: {uri}\n\
+             Version: 1\n\
+             Chain ID: {chain_id}\n\
+             Nonce: abcdefgh12345678\n\
+             {times}"
+        )
+        .parse()
+        .unwrap()
+    }
+
+    fn valid_message(times: &str) -> Message {
+        message(
+            "app.example.com",
+            "https://app.example.com/api",
+            534352,
+            times,
+        )
+    }
+
+    fn error_data(result: RpcResult<()>) -> String {
+        result.unwrap_err().data().unwrap().get().to_string()
+    }
+
+    #[test]
+    fn test_has_uri_prefix() {
+        assert!(has_uri_prefix("https://a.com", "https://a.com"));
+        assert!(has_uri_prefix("https://a.com/api", "https://a.com"));
+        assert!(has_uri_prefix("https://a.com/api", "https://a.com/"));
+        assert!(has_uri_prefix("https://a.com?x=1", "https://a.com"));
+        assert!(!has_uri_prefix("https://a.com.evil.io", "https://a.com"));
+        assert!(!has_uri_prefix("https://b.com/api", "https://a.com/"));
+    }
+
+    #[test]
+    fn test_verify_message_fields() {
+        let cfg = config();
+
+        // Valid message
+        let msg = valid_message("Issued At: 2025-10-09T08:53:00Z");
+        assert!(verify_message_fields(&cfg, &msg, NOW).is_ok());
+
+        // Valid message with time bounds
+        let msg = valid_message(
+            "Issued At: 2025-10-09T08:53:00Z\n\
+             Expiration Time: 2025-10-09T09:53:00Z\n\
+             Not Before: 2025-10-09T08:53:00Z",
+        );
+        assert!(verify_message_fields(&cfg, &msg, NOW).is_ok());
+
+        // Wrong domain
+        let msg = message(
+            "evil.example.com",
+            "https://app.example.com/api",
+            534352,
+            "Issued At: 2025-10-09T08:53:00Z",
+        );
+        let err = error_data(verify_message_fields(&cfg, &msg, NOW));
+        assert!(err.contains("invalid message domain"));
+
+        // Wrong URI
+        let msg = message(
+            "app.example.com",
+            "https://evil.example.com/api",
+            534352,
+            "Issued At: 2025-10-09T08:53:00Z",
+        );
+        let err = error_data(verify_message_fields(&cfg, &msg, NOW));
+        assert!(err.contains("invalid message URI"));
+
+        // Wrong chain id
+        let msg = message(
+            "app.example.com",
+            "https://app.example.com/api",
+            1,
+            "Issued At: 2025-10-09T08:53:00Z",
+        );
+        let err = error_data(verify_message_fields(&cfg, &msg, NOW));
+        assert!(err.contains("invalid message Chain ID"));
+
+        // Issued in the future
+        let msg = valid_message("Issued At: 2025-10-09T09:00:00Z");
+        let err = error_data(verify_message_fields(&cfg, &msg, NOW));
+        assert!(err.contains("invalid message Issued At"));
+
+        // Issued too long ago
+        let msg = valid_message("Issued At: 2025-10-09T08:00:00Z");
+        let err = error_data(verify_message_fields(&cfg, &msg, NOW));
+        assert!(err.contains("invalid message Issued At"));
+
+        // Expired
+        let msg = valid_message(
+            "Issued At: 2025-10-09T08:53:00Z\n\
+             Expiration Time: 2025-10-09T08:53:10Z",
+        );
+        let err = error_data(verify_message_fields(&cfg, &msg, NOW));
+        assert!(err.contains("invalid message Expiration Time"));
+
+        // Not valid yet
+        let msg = valid_message(
+            "Issued At: 2025-10-09T08:53:00Z\n\
+             Not Before: 2025-10-09T09:53:00Z",
+        );
+        let err = error_data(verify_message_fields(&cfg, &msg, NOW));
+        assert!(err.contains("invalid message Not Before"));
+
+        // Without a `[siwe]` section any domain, URI and chain id is accepted
+        let msg = message(
+            "evil.example.com",
+            "https://evil.example.com/api",
+            1,
+            "Issued At: 2025-10-09T08:53:00Z",
+        );
+        assert!(verify_message_fields(&SiweConfig::default(), &msg, NOW).is_ok());
+        let msg = valid_message("Issued At: 2025-10-09T09:00:00Z");
+        assert!(verify_message_fields(&SiweConfig::default(), &msg, NOW).is_err());
+    }
+
+    // Address used in the test messages
+    const WALLET: Address =
+        alloy::primitives::address!("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+
+    // Counterfactual deployment of `WALLET` used in the EIP-6492 tests
+    const FACTORY: Address = Address::repeat_byte(0x11);
+
+    fn factory_calldata() -> Bytes {
+        Bytes::from(vec![0x22; 68])
+    }
+
+    /// Start a mock validium upstream whose `eth_call` implements `isValidSignature`
+    /// for `WALLET`, accepting only `valid_signature`. `WALLET` is deployed only if
+    /// `deployed` is set, otherwise it can be deployed through `FACTORY`.
+    async fn mock_upstream(valid_signature: Bytes, deployed: bool) -> (HttpClient, ServerHandle) {
+        let mut module = RpcModule::new(());
+        module
+            .register_method("eth_call", move |params, _, _| -> RpcResult<Bytes> {
+                let (request, _block): (TransactionRequest, String) = params.parse()?;
+                let input = request.input.input().cloned().unwrap_or_default();
+
+                let (wallet_deployed, wallet, input) =
+                    match request.to.and_then(|to| to.to().copied()) {
+                        // EIP-6492 validator, simulate the deployment
+                        None => {
+                            let (factory, calldata, wallet, input) = parse_validator_code(&input);
+                            let deployed =
+                                deployed || (factory == FACTORY && calldata == factory_calldata());
+                            (deployed, wallet, input)
+                        }
+                        Some(to) => (deployed, to, input),
+                    };
+
+                // not a contract
+                if wallet != WALLET || !wallet_deployed {
+                    return Ok(Bytes::new());
+                }
+
+                let call = IERC1271::isValidSignatureCall::abi_decode(&input)
+                    .map_err(|e| invalid_params(e.to_string()))?;
+
+                let mut output = [0u8; 32];
+                if call.signature == valid_signature {
+                    output[..4].copy_from_slice(&EIP1271_MAGIC_VALUE);
+                }
+
+                // the EIP-6492 validator also returns the call status
+                if request.to.is_none() {
+                    let mut with_status = [0u8; 64];
+                    with_status[..32].copy_from_slice(&output);
+                    with_status[63] = 1;
+                    return Ok(Bytes::copy_from_slice(&with_status));
+                }
+                Ok(Bytes::copy_from_slice(&output))
+            })
+            .unwrap();
+
+        let (url, handle) = mock_server(module).await;
+        (HttpClient::builder().build(url).unwrap(), handle)
+    }
+
+    fn auth_server(validium_client: HttpClient) -> SiweAuthRpcImpl {
+        let jwt = JwtSigner::from_config(
+            &[JwtSignerKeyConfig {
+                kid: "test".to_string(),
+                secret: "testsecret-testsecret-testsecret".to_string(),
+                ..Default::default()
+            }],
+            "test",
+        )
+        .unwrap();
+        let revoked_tokens = Arc::new(InMemoryRevocationList::new());
+        SiweAuthRpcImpl::new(jwt, 3600, 86400, revoked_tokens, config(), validium_client)
+    }
+
+    async fn sign_in_message(auth: &SiweAuthRpcImpl) -> String {
+        // register the nonce used by `message`
+        auth.cache.insert("abcdefgh12345678".to_string(), ()).await;
+
+        let issued_at = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+        valid_message(&format!("Issued At: {issued_at}")).to_string()
+    }
+
+    #[tokio::test]
+    async fn test_sign_in_eip1271() {
+        let valid_signature = Bytes::from(vec![0xab; 100]);
+        let (client, _handle) = mock_upstream(valid_signature.clone(), true).await;
+        let auth = auth_server(client);
+
+        // Contract wallet approves the signature
+        let message = sign_in_message(&auth).await;
+        let token = auth.sign_in(message, valid_signature).await.unwrap();
+        let claims = auth.jwt.decode_token(token).unwrap();
+        assert_eq!(claims.address, WALLET);
+
+        // Contract wallet rejects the signature
+        let message = sign_in_message(&auth).await;
+        let err = auth
+            .sign_in(message, Bytes::from(vec![0xcd; 100]))
+            .await
+            .unwrap_err();
+        assert!(
+            err.data()
+                .unwrap()
+                .get()
+                .contains("invalid message or signature")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sign_in_eip6492() {
+        let valid_signature = Bytes::from(vec![0xab; 65]);
+        let (client, _handle) = mock_upstream(valid_signature.clone(), false).await;
+        let auth = auth_server(client);
+
+        // Undeployed wallet, deployed through the factory
+        let message = sign_in_message(&auth).await;
+        let signature = wrap(FACTORY, &factory_calldata(), &valid_signature);
+        let token = auth.sign_in(message, signature).await.unwrap();
+        let claims = auth.jwt.decode_token(token).unwrap();
+        assert_eq!(claims.address, WALLET);
+
+        // Undeployed wallet, plain EIP-1271 signature
+        let message = sign_in_message(&auth).await;
+        assert!(
+            auth.sign_in(message, valid_signature.clone())
+                .await
+                .is_err()
+        );
+
+        // Undeployed wallet, wrong factory
+        let message = sign_in_message(&auth).await;
+        let signature = wrap(
+            Address::repeat_byte(0x99),
+            &factory_calldata(),
+            &valid_signature,
+        );
+        assert!(auth.sign_in(message, signature).await.is_err());
+
+        // Undeployed wallet, invalid inner signature
+        let message = sign_in_message(&auth).await;
+        let signature = wrap(FACTORY, &factory_calldata(), &Bytes::from(vec![0xcd; 65]));
+        assert!(auth.sign_in(message, signature).await.is_err());
+
+        // Malformed wrapper
+        let message = sign_in_message(&auth).await;
+        let mut signature = vec![0xab; 10];
+        signature.extend_from_slice(&crate::auth::eip6492::EIP6492_MAGIC_SUFFIX);
+        let err = auth
+            .sign_in(message, Bytes::from(signature))
+            .await
+            .unwrap_err();
+        assert!(
+            err.data()
+                .unwrap()
+                .get()
+                .contains("invalid EIP-6492 signature")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_refresh() {
+        let valid_signature = Bytes::from(vec![0xab; 100]);
+        let (client, _handle) = mock_upstream(valid_signature.clone(), true).await;
+        let auth = auth_server(client);
+
+        let message = sign_in_message(&auth).await;
+        let tokens = auth
+            .sign_in_with_refresh(message, valid_signature)
+            .await
+            .unwrap();
+
+        // Exchange the refresh token for a new pair
+        let refreshed = auth.refresh(tokens.refresh_token.clone()).await.unwrap();
+        let claims = auth.jwt.decode_token(&refreshed.access_token).unwrap();
+        assert_eq!(claims.address, WALLET);
+        assert_ne!(refreshed.refresh_token, tokens.refresh_token);
+
+        // Reusing the old refresh token revokes the new one as well
+        let err = auth.refresh(tokens.refresh_token).await.unwrap_err();
+        assert!(err.data().unwrap().get().contains("already used"));
+        assert!(auth.refresh(refreshed.refresh_token).await.is_err());
+
+        // Unknown refresh token
+        let err = auth.refresh("unknown".to_string()).await.unwrap_err();
+        assert!(err.data().unwrap().get().contains("invalid refresh token"));
+    }
+
+    #[tokio::test]
+    async fn test_sign_out() {
+        let valid_signature = Bytes::from(vec![0xab; 100]);
+        let (client, _handle) = mock_upstream(valid_signature.clone(), true).await;
+        let auth = auth_server(client);
+
+        let message = sign_in_message(&auth).await;
+        let tokens = auth
+            .sign_in_with_refresh(message, valid_signature)
+            .await
+            .unwrap();
+        let claims = auth.jwt.decode_token(&tokens.access_token).unwrap();
+        let jti = claims.jti.unwrap();
+        assert!(!auth.revoked_tokens.is_revoked(&jti).await);
+
+        auth.sign_out(tokens.access_token, Some(tokens.refresh_token.clone()))
+            .await
+            .unwrap();
+
+        // Both the access token and the refresh token are revoked
+        assert!(auth.revoked_tokens.is_revoked(&jti).await);
+        assert!(auth.refresh(tokens.refresh_token).await.is_err());
+
+        // Invalid access token
+        let err = auth
+            .sign_out("invalid".to_string(), None)
+            .await
+            .unwrap_err();
+        assert!(err.data().unwrap().get().contains("invalid access token"));
+    }
+
+    #[tokio::test]
+    async fn test_sign_out_expired_access_token() {
+        let valid_signature = Bytes::from(vec![0xab; 100]);
+        let (client, _handle) = mock_upstream(valid_signature.clone(), true).await;
+        let auth = auth_server(client);
+
+        let message = sign_in_message(&auth).await;
+        let tokens = auth
+            .sign_in_with_refresh(message, valid_signature)
+            .await
+            .unwrap();
+
+        // The access token expired, beyond the leeway, the refresh token is still revoked
+        let expired = (Utc::now().timestamp() - 120) as usize;
+        let access_token = auth.jwt.create_token(WALLET, expired).unwrap();
+        auth.sign_out(access_token, Some(tokens.refresh_token.clone()))
+            .await
+            .unwrap();
+        assert!(auth.refresh(tokens.refresh_token).await.is_err());
+    }
 }
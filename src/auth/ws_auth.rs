@@ -0,0 +1,60 @@
+use chrono::Utc;
+use hyper::http::Extensions;
+use jsonrpsee::core::server::ConnectionId;
+use jsonrpsee::core::{RpcResult, async_trait};
+use jsonrpsee::proc_macros::rpc;
+
+use super::auth_middleware::AuthenticationMiddleware;
+use super::error::{invalid_token, websocket_only};
+use super::session::{WebSocketConnection, WsSessions};
+
+#[rpc(server, namespace = "ws")]
+pub trait WsAuthRpc {
+    /// Authenticate a WebSocket connection with an access token or API key, for clients
+    /// that can't set the authorization header on the connection upgrade.
+    /// Returns the expiry of the token, the connection is anonymous again after it.
+    #[method(name = "authenticate", with_extensions)]
+    async fn authenticate(&self, token: String) -> RpcResult<Option<i64>>;
+}
+
+pub struct WsAuthRpcImpl {
+    authenticator: AuthenticationMiddleware,
+    sessions: WsSessions,
+}
+
+impl WsAuthRpcImpl {
+    pub fn new(authenticator: AuthenticationMiddleware, sessions: WsSessions) -> Self {
+        Self {
+            authenticator,
+            sessions,
+        }
+    }
+}
+
+#[async_trait]
+impl WsAuthRpcServer for WsAuthRpcImpl {
+    async fn authenticate(&self, ext: &Extensions, token: String) -> RpcResult<Option<i64>> {
+        let conn = match (ext.get::<WebSocketConnection>(), ext.get::<ConnectionId>()) {
+            (Some(_), Some(conn)) => *conn,
+            _ => return Err(websocket_only()),
+        };
+
+        let session = self
+            .authenticator
+            .authenticate_token(&token)
+            .await
+            .map_err(|e| {
+                debug!("Rejecting WebSocket authentication: {e}");
+                invalid_token(e.reason())
+            })?;
+
+        // JWT validation has some leeway, the session of such a token would already be expired
+        if session.is_expired(Utc::now().timestamp()) {
+            return Err(invalid_token("token expired"));
+        }
+
+        let expires_at = session.expires_at;
+        self.sessions.insert(conn, session);
+        Ok(expires_at)
+    }
+}
@@ -1,9 +1,15 @@
-use clap::Parser;
+use alloy::primitives::B256;
+use clap::{Parser, Subcommand};
+
+use crate::proxy::TRANSFER_EVENT;
 
 /// Command line arguments
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
-struct CliArgs {
+pub struct CliArgs {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Path to config file (default: config.toml)
     #[arg(long, default_value = "config.toml")]
     config: String,
@@ -19,6 +25,20 @@ struct CliArgs {
     /// Withdraw-proofs RPC endpoint to relay proxy requests to, e.g. http://cloak-withdraw-proofs:8545
     #[arg(long)]
     withdraw_proofs_url: Option<String>,
+
+    /// Validium WebSocket endpoint for subscriptions, e.g. ws://validium-sequencer:8546
+    #[arg(long)]
+    validium_ws_url: Option<String>,
+}
+
+/// Commands other than running the server
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Hash an API key for `admin_keys` or `api_keys`, a random key is generated if not given
+    HashKey {
+        /// The key to hash
+        key: Option<String>,
+    },
 }
 
 /// Structure of the config file
@@ -26,13 +46,54 @@ struct CliArgs {
 pub struct AppConfig {
     #[serde(default = "default_bind_address")]
     pub bind_address: String,
+    /// Local address of the Prometheus `/metrics` listener, metrics are not served if not set
+    pub metrics_address: Option<String>,
     #[serde(default = "default_validium_url")]
     pub validium_url: String,
     pub withdraw_proofs_url: String,
+    /// WebSocket endpoint of the validium node, `eth_subscribe` is disabled if not set
+    pub validium_ws_url: Option<String>,
     pub admin_keys: Vec<String>,
+    #[serde(default)]
+    pub api_keys: Vec<super::auth::ApiKeyConfig>,
     pub jwt_expiry_secs: usize,
+    #[serde(default = "default_refresh_token_expiry_secs")]
+    pub refresh_token_expiry_secs: usize,
+    #[serde(default)]
+    pub reject_invalid_tokens: bool,
     pub default_kid: String,
     pub jwt_signer_keys: Vec<super::auth::JwtSignerKeyConfig>,
+    #[serde(default)]
+    pub siwe: super::auth::SiweConfig,
+    #[serde(default)]
+    pub tx_whitelist: Vec<super::proxy::TxWhitelistEntryConfig>,
+    /// Read-only functions basic users may call with `eth_call`
+    #[serde(default)]
+    pub call_allowlist: Vec<super::proxy::CallAllowlistEntryConfig>,
+    /// TOML file overriding the built-in access rules of RPC methods
+    pub access_policy_file: Option<String>,
+    /// Methods relayed to the validium node as is
+    #[serde(default)]
+    pub passthrough_methods: Vec<super::proxy::PassthroughMethodConfig>,
+    #[serde(default)]
+    pub max_batch_size: super::service::MaxBatchSize,
+    /// Blocks an `eth_getLogs` call may search, for users without full access
+    #[serde(default = "default_max_logs_block_range")]
+    pub max_logs_block_range: u64,
+    /// Only return the receipt logs involving the caller, for users without full access
+    #[serde(default)]
+    pub redact_receipt_logs: bool,
+    /// Signatures (topic 0) of events whose parties may view the transaction that emitted them
+    #[serde(default = "default_transfer_events")]
+    pub transfer_events: Vec<B256>,
+    /// Transaction history index, `scroll_getTransactionsByAddress` is disabled if not set
+    pub history: Option<super::history::HistoryConfig>,
+    /// Delay between reporting not ready and refusing connections on shutdown
+    #[serde(default = "default_shutdown_delay_secs")]
+    pub shutdown_delay_secs: u64,
+    /// Time allowed for in-flight requests to complete on shutdown
+    #[serde(default = "default_shutdown_timeout_secs")]
+    pub shutdown_timeout_secs: u64,
 }
 
 /// Default bind address if not specified anywhere
@@ -45,10 +106,33 @@ fn default_validium_url() -> String {
     "http://validium-sequencer:8545".to_owned()
 }
 
-/// Load configuration from CLI, config file, and defaults
-pub fn load_config() -> anyhow::Result<AppConfig> {
-    let args = CliArgs::parse();
+/// Default refresh token expiry (30 days) if not specified
+fn default_refresh_token_expiry_secs() -> usize {
+    30 * 24 * 3600
+}
+
+/// Default `eth_getLogs` block range limit if not specified
+fn default_max_logs_block_range() -> u64 {
+    10_000
+}
+
+/// Default delay for load balancers to notice the shutdown if not specified
+fn default_shutdown_delay_secs() -> u64 {
+    10
+}
 
+/// Default time allowed for in-flight requests on shutdown if not specified
+fn default_shutdown_timeout_secs() -> u64 {
+    30
+}
+
+/// Default transfer events if not specified: ERC-20 and ERC-721 `Transfer(address,address,uint256)`
+fn default_transfer_events() -> Vec<B256> {
+    vec![TRANSFER_EVENT]
+}
+
+/// Load configuration from CLI, config file, and defaults
+pub fn load_config(args: CliArgs) -> anyhow::Result<AppConfig> {
     let mut cfg: AppConfig = config::Config::builder()
         .add_source(config::File::from(args.config.as_ref()).required(true))
         .build()?
@@ -64,6 +148,9 @@ pub fn load_config() -> anyhow::Result<AppConfig> {
     if let Some(val) = args.withdraw_proofs_url {
         cfg.withdraw_proofs_url = val;
     }
+    if let Some(val) = args.validium_ws_url {
+        cfg.validium_ws_url = Some(val);
+    }
 
     // Validate bind_address format
     cfg.bind_address
@@ -75,6 +162,13 @@ pub fn load_config() -> anyhow::Result<AppConfig> {
             )
         })?;
 
+    // Validate metrics_address format
+    if let Some(address) = &cfg.metrics_address
+        && address.parse::<std::net::SocketAddr>().is_err()
+    {
+        anyhow::bail!("Invalid metrics_address: {address}. Expected format like 127.0.0.1:9090");
+    }
+
     // Validate validium_url format
     if !cfg.validium_url.starts_with("http://") && !cfg.validium_url.starts_with("https://") {
         anyhow::bail!(
@@ -93,6 +187,14 @@ pub fn load_config() -> anyhow::Result<AppConfig> {
         );
     }
 
+    // Validate validium_ws_url format
+    if let Some(url) = &cfg.validium_ws_url
+        && !url.starts_with("ws://")
+        && !url.starts_with("wss://")
+    {
+        anyhow::bail!("Invalid validium_ws_url: {url}. Must start with ws:// or wss://");
+    }
+
     Ok(cfg)
 }
 
@@ -106,19 +208,56 @@ mod tests {
         // Example TOML configuration string
         let toml = r#"
             bind_address = "127.0.0.1:12345"
+            metrics_address = "127.0.0.1:9090"
             validium_url = "http://example.com:8545"
             withdraw_proofs_url = "http://example.com:8546"
+            validium_ws_url = "ws://example.com:8547"
             admin_keys = [
               "admin-token-1-abcdefg",
               "admin-token-2-hijklmn"
             ]
+            api_keys = [
+              { name = "partner", key = "partner-key", methods = ["eth_getBalance"], addresses = ["0x1234567890abcdef1234567890abcdef12345678"], read_only = true, expires_at = "2030-01-01T00:00:00Z" }
+            ]
             jwt_expiry_secs = 3600
+            refresh_token_expiry_secs = 86400
+            reject_invalid_tokens = true
             default_kid = "key-2025-07"
 
             jwt_signer_keys = [
               { kid = "key-2025-07", secret = "supersecret1" },
               { kid = "key-2025-06", secret = "supersecret2" }
             ]
+
+            tx_whitelist = [
+              { to = "0x1234567890abcdef1234567890abcdef12345678", selectors = ["0xa9059cbb", "0x095ea7b3"] }
+            ]
+
+            call_allowlist = [
+              { to = "0x1234567890abcdef1234567890abcdef12345678", function = "allowance(address,address)", args = ["self", "any"] }
+            ]
+
+            access_policy_file = "policy.toml"
+
+            passthrough_methods = [
+              { method = "net_version", access = "public", read_only = true },
+              { method = "eth_getBlockTransactionCountByNumber", access = "full" }
+            ]
+
+            max_batch_size = { none = 10, basic = 50 }
+            max_logs_block_range = 5000
+            redact_receipt_logs = true
+            shutdown_delay_secs = 5
+            transfer_events = ["0xc3d58168c5ae7397731d063d5bbf3d657854427343f4c083240f7aacaa2d0f62"]
+
+            [history]
+            path = "/data/history.redb"
+            start_block = 100
+
+            [siwe]
+            domains = ["app.example.com"]
+            uri_prefix = "https://app.example.com/"
+            chain_id = 534352
         "#;
 
         // Parse config from string
@@ -131,8 +270,13 @@ mod tests {
 
         // Check values
         assert_eq!(cfg.bind_address, "127.0.0.1:12345");
+        assert_eq!(cfg.metrics_address.as_deref(), Some("127.0.0.1:9090"));
         assert_eq!(cfg.validium_url, "http://example.com:8545");
         assert_eq!(cfg.withdraw_proofs_url, "http://example.com:8546");
+        assert_eq!(
+            cfg.validium_ws_url.as_deref(),
+            Some("ws://example.com:8547")
+        );
         assert_eq!(
             cfg.admin_keys,
             vec![
@@ -140,12 +284,70 @@ mod tests {
                 "admin-token-2-hijklmn".to_string()
             ]
         );
+        assert_eq!(cfg.api_keys.len(), 1);
+        assert_eq!(cfg.api_keys[0].name, "partner");
+        assert_eq!(cfg.api_keys[0].key, "partner-key");
+        assert_eq!(
+            cfg.api_keys[0].methods,
+            Some(vec!["eth_getBalance".to_string()])
+        );
+        assert_eq!(cfg.api_keys[0].addresses.as_ref().unwrap().len(), 1);
+        assert!(cfg.api_keys[0].read_only);
+        assert!(cfg.api_keys[0].enabled);
+        assert_eq!(
+            cfg.api_keys[0].expires_at.as_deref(),
+            Some("2030-01-01T00:00:00Z")
+        );
         assert_eq!(cfg.jwt_expiry_secs, 3600);
+        assert_eq!(cfg.refresh_token_expiry_secs, 86400);
+        assert!(cfg.reject_invalid_tokens);
         assert_eq!(cfg.default_kid, "key-2025-07".to_string());
         assert_eq!(cfg.jwt_signer_keys.len(), 2);
         assert_eq!(cfg.jwt_signer_keys[0].kid, "key-2025-07");
         assert_eq!(cfg.jwt_signer_keys[0].secret, "supersecret1");
         assert_eq!(cfg.jwt_signer_keys[1].kid, "key-2025-06");
         assert_eq!(cfg.jwt_signer_keys[1].secret, "supersecret2");
+        assert_eq!(cfg.tx_whitelist.len(), 1);
+        assert_eq!(
+            cfg.tx_whitelist[0].to.to_string().to_lowercase(),
+            "0x1234567890abcdef1234567890abcdef12345678"
+        );
+        assert_eq!(
+            cfg.tx_whitelist[0]
+                .selectors
+                .iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>(),
+            vec!["0xa9059cbb".to_string(), "0x095ea7b3".to_string()]
+        );
+        assert_eq!(cfg.call_allowlist.len(), 1);
+        assert_eq!(cfg.call_allowlist[0].function, "allowance(address,address)");
+        assert_eq!(cfg.call_allowlist[0].args.len(), 2);
+        assert_eq!(cfg.access_policy_file.as_deref(), Some("policy.toml"));
+        assert_eq!(cfg.passthrough_methods.len(), 2);
+        assert_eq!(cfg.passthrough_methods[0].method, "net_version");
+        assert!(cfg.passthrough_methods[0].read_only);
+        assert!(!cfg.passthrough_methods[1].read_only);
+        assert_eq!(
+            cfg.passthrough_methods[1].access,
+            crate::auth::AccessRule::Full
+        );
+        assert_eq!(cfg.max_batch_size.none, Some(10));
+        assert_eq!(cfg.max_batch_size.basic, Some(50));
+        assert_eq!(cfg.max_batch_size.full, None);
+        assert_eq!(cfg.max_logs_block_range, 5000);
+        assert!(cfg.redact_receipt_logs);
+        assert_eq!(cfg.transfer_events.len(), 1);
+        assert_eq!(cfg.shutdown_delay_secs, 5);
+        assert_eq!(cfg.shutdown_timeout_secs, 30);
+        assert_ne!(cfg.transfer_events, default_transfer_events());
+        let history = cfg.history.unwrap();
+        assert_eq!(history.path, "/data/history.redb");
+        assert_eq!(history.start_block, 100);
+        assert_eq!(history.poll_interval_secs, 2);
+        assert_eq!(cfg.siwe.domains, vec!["app.example.com".to_string()]);
+        assert_eq!(cfg.siwe.uri_prefix, "https://app.example.com/");
+        assert_eq!(cfg.siwe.chain_id, Some(534352));
+        assert_eq!(cfg.siwe.max_clock_skew_secs, 60);
     }
 }
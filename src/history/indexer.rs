@@ -0,0 +1,108 @@
+use std::time::Duration;
+
+use alloy_network_primitives::TransactionResponse;
+use alloy_rpc_types::{BlockNumberOrTag, TransactionTrait};
+use jsonrpsee::http_client::HttpClient;
+use serde::Deserialize;
+
+use super::store::{HistoryStore, IndexedTransaction, TransactionPosition};
+use crate::proxy::EthRpcProxyClient;
+
+/// Configuration of the transaction history index
+#[derive(Debug, Deserialize)]
+pub struct HistoryConfig {
+    /// Path of the index database, created if it does not exist
+    pub path: String,
+    /// First block to index when the database is created
+    #[serde(default)]
+    pub start_block: u64,
+    /// Delay between checks for new blocks
+    #[serde(default = "default_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+}
+
+fn default_poll_interval_secs() -> u64 {
+    2
+}
+
+/// Follows the validium chain and indexes the sender and receiver of each transaction
+pub struct HistoryIndexer {
+    validium_client: HttpClient,
+    store: HistoryStore,
+    start_block: u64,
+    poll_interval: Duration,
+}
+
+impl HistoryIndexer {
+    pub fn new(validium_client: HttpClient, store: HistoryStore, config: &HistoryConfig) -> Self {
+        Self {
+            validium_client,
+            store,
+            start_block: config.start_block,
+            poll_interval: Duration::from_secs(config.poll_interval_secs),
+        }
+    }
+
+    /// Index new blocks until the server stops, errors are retried on the next poll
+    pub async fn run(self) {
+        loop {
+            if let Err(e) = self.index_new_blocks().await {
+                warn!("History indexer: {e}");
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    /// Index the blocks up to the finalized block, so the index never sees a reorg
+    async fn index_new_blocks(&self) -> anyhow::Result<()> {
+        let head = EthRpcProxyClient::block_by_number(
+            &self.validium_client,
+            BlockNumberOrTag::Finalized,
+            false,
+        )
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("no finalized block"))?
+        .header
+        .number;
+        let mut next = match self.store.last_block()? {
+            Some(last) => last + 1,
+            None => self.start_block,
+        };
+
+        while next <= head {
+            let block = EthRpcProxyClient::block_by_number(
+                &self.validium_client,
+                BlockNumberOrTag::Number(next),
+                true,
+            )
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("block {next} not found"))?;
+
+            let transactions = block
+                .transactions
+                .txns()
+                .enumerate()
+                .map(|(i, tx)| IndexedTransaction {
+                    hash: tx.tx_hash(),
+                    position: TransactionPosition {
+                        block_number: next,
+                        transaction_index: i as u32,
+                    },
+                    from: tx.from(),
+                    to: tx.to(),
+                })
+                .collect::<Vec<_>>();
+            debug!(
+                "Indexing block {next} with {} transactions",
+                transactions.len()
+            );
+
+            // writes are synced to disk
+            let store = self.store.clone();
+            tokio::task::spawn_blocking(move || store.index_block(next, &transactions)).await??;
+            next += 1;
+        }
+
+        Ok(())
+    }
+}
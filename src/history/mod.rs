@@ -0,0 +1,7 @@
+mod indexer;
+mod rpc;
+mod store;
+
+pub use indexer::{HistoryConfig, HistoryIndexer};
+pub use rpc::{HistoryRpcImpl, HistoryRpcServer};
+pub use store::HistoryStore;
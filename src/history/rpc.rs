@@ -0,0 +1,178 @@
+use alloy::primitives::{Address, B256, U64};
+use hyper::http::Extensions;
+use jsonrpsee::core::{RpcResult, async_trait};
+use jsonrpsee::proc_macros::rpc;
+use serde::{Deserialize, Serialize};
+
+use super::store::{HistoryStore, IndexedTransaction, TransactionPosition};
+use crate::auth::{get_access, internal_error, invalid_params, unauthorized};
+
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 100;
+
+/// A transaction sent or received by the queried address
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressTransaction {
+    pub hash: B256,
+    pub block_number: U64,
+    pub transaction_index: U64,
+    pub from: Address,
+    pub to: Option<Address>,
+}
+
+impl From<IndexedTransaction> for AddressTransaction {
+    fn from(tx: IndexedTransaction) -> Self {
+        Self {
+            hash: tx.hash,
+            block_number: U64::from(tx.position.block_number),
+            transaction_index: U64::from(tx.position.transaction_index),
+            from: tx.from,
+            to: tx.to,
+        }
+    }
+}
+
+/// A page of transactions, newest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionsPage {
+    pub transactions: Vec<AddressTransaction>,
+    /// Pass to the next call to get older transactions, `None` on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// Cursors are the position of the last returned transaction, "<block number>:<transaction index>"
+fn parse_cursor(cursor: &str) -> Option<TransactionPosition> {
+    let (block_number, transaction_index) = cursor.split_once(':')?;
+    Some(TransactionPosition {
+        block_number: block_number.parse().ok()?,
+        transaction_index: transaction_index.parse().ok()?,
+    })
+}
+
+fn format_cursor(position: &TransactionPosition) -> String {
+    format!("{}:{}", position.block_number, position.transaction_index)
+}
+
+#[rpc(server, namespace = "scroll")]
+pub trait HistoryRpc {
+    #[method(name = "getTransactionsByAddress", with_extensions)]
+    async fn transactions_by_address(
+        &self,
+        address: Address,
+        cursor: Option<String>,
+        limit: Option<usize>,
+    ) -> RpcResult<TransactionsPage>;
+}
+
+pub struct HistoryRpcImpl {
+    store: HistoryStore,
+}
+
+impl HistoryRpcImpl {
+    pub fn new(store: HistoryStore) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl HistoryRpcServer for HistoryRpcImpl {
+    async fn transactions_by_address(
+        &self,
+        ext: &Extensions,
+        address: Address,
+        cursor: Option<String>,
+        limit: Option<usize>,
+    ) -> RpcResult<TransactionsPage> {
+        // check the rule here too, in case the method is reached without `AccessPolicyMiddleware`
+        if !get_access(ext).is_authorized(&address) {
+            return Err(unauthorized());
+        }
+
+        let before = cursor
+            .map(|c| parse_cursor(&c).ok_or_else(|| invalid_params("invalid cursor")))
+            .transpose()?;
+        let limit = limit.unwrap_or(DEFAULT_LIMIT);
+        if limit == 0 || limit > MAX_LIMIT {
+            return Err(invalid_params(format!(
+                "limit must be between 1 and {MAX_LIMIT}"
+            )));
+        }
+
+        let transactions = self
+            .store
+            .transactions(&address, before, limit)
+            .map_err(|e| {
+                error!("History store error: {e}");
+                internal_error("history not available")
+            })?;
+
+        let next_cursor = match transactions.last() {
+            Some(last) if transactions.len() == limit => Some(format_cursor(&last.position)),
+            _ => None,
+        };
+        Ok(TransactionsPage {
+            transactions: transactions.into_iter().map(Into::into).collect(),
+            next_cursor,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::AccessLevel;
+    use redb::Database;
+    use redb::backends::InMemoryBackend;
+
+    #[test]
+    fn test_cursor() {
+        let position = TransactionPosition {
+            block_number: 12,
+            transaction_index: 3,
+        };
+        assert_eq!(format_cursor(&position), "12:3");
+        assert_eq!(parse_cursor("12:3"), Some(position));
+        assert_eq!(parse_cursor("12"), None);
+        assert_eq!(parse_cursor("12:x"), None);
+        assert_eq!(parse_cursor("-1:0"), None);
+    }
+
+    #[tokio::test]
+    async fn test_transactions_by_address_authorization() {
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap();
+        let rpc = HistoryRpcImpl::new(HistoryStore::new(db).unwrap());
+        let alice = Address::repeat_byte(0x11);
+        let bob = Address::repeat_byte(0x22);
+
+        let mut ext = Extensions::new();
+        ext.insert(AccessLevel::Basic(alice));
+        let page = rpc
+            .transactions_by_address(&ext, alice, None, None)
+            .await
+            .unwrap();
+        assert!(page.transactions.is_empty());
+
+        // Without the middleware stack, the handler still checks the address
+        let err = rpc
+            .transactions_by_address(&ext, bob, None, None)
+            .await
+            .unwrap_err();
+        assert_eq!(err, unauthorized());
+        let err = rpc
+            .transactions_by_address(&Extensions::new(), alice, None, None)
+            .await
+            .unwrap_err();
+        assert_eq!(err, unauthorized());
+
+        ext.insert(AccessLevel::Full);
+        assert!(
+            rpc.transactions_by_address(&ext, bob, None, None)
+                .await
+                .is_ok()
+        );
+    }
+}
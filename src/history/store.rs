@@ -0,0 +1,236 @@
+use std::sync::Arc;
+
+use alloy::primitives::{Address, B256};
+use redb::{Database, ReadableDatabase, ReadableTable, TableDefinition};
+
+/// Transactions of each address, keyed by address, block number and transaction index
+/// (big endian, so keys of an address are in chain order), see `TransactionKey`
+const ADDRESS_TRANSACTIONS: TableDefinition<[u8; 32], [u8; 73]> =
+    TableDefinition::new("address_transactions");
+
+/// Indexing progress
+const META: TableDefinition<&str, u64> = TableDefinition::new("meta");
+
+const LAST_BLOCK: &str = "last_block";
+
+/// Position of a transaction in the chain
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TransactionPosition {
+    pub block_number: u64,
+    pub transaction_index: u32,
+}
+
+impl TransactionPosition {
+    const MAX: Self = Self {
+        block_number: u64::MAX,
+        transaction_index: u32::MAX,
+    };
+}
+
+/// A transaction sent or received by an address
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndexedTransaction {
+    pub hash: B256,
+    pub position: TransactionPosition,
+    pub from: Address,
+    /// `None` for contract deployments
+    pub to: Option<Address>,
+}
+
+fn transaction_key(address: &Address, position: TransactionPosition) -> [u8; 32] {
+    let mut key = [0; 32];
+    key[..20].copy_from_slice(address.as_slice());
+    key[20..28].copy_from_slice(&position.block_number.to_be_bytes());
+    key[28..].copy_from_slice(&position.transaction_index.to_be_bytes());
+    key
+}
+
+impl IndexedTransaction {
+    fn encode(&self) -> [u8; 73] {
+        let mut value = [0; 73];
+        value[..32].copy_from_slice(self.hash.as_slice());
+        value[32..52].copy_from_slice(self.from.as_slice());
+        if let Some(to) = self.to {
+            value[52..72].copy_from_slice(to.as_slice());
+            value[72] = 1;
+        }
+        value
+    }
+
+    fn decode(key: &[u8; 32], value: &[u8; 73]) -> Self {
+        Self {
+            hash: B256::from_slice(&value[..32]),
+            position: TransactionPosition {
+                block_number: u64::from_be_bytes(key[20..28].try_into().expect("8 bytes")),
+                transaction_index: u32::from_be_bytes(key[28..].try_into().expect("4 bytes")),
+            },
+            from: Address::from_slice(&value[32..52]),
+            to: (value[72] == 1).then(|| Address::from_slice(&value[52..72])),
+        }
+    }
+}
+
+/// Embedded store of the transactions of each address
+#[derive(Clone)]
+pub struct HistoryStore {
+    db: Arc<Database>,
+}
+
+impl HistoryStore {
+    /// Open or create the store at `path`
+    pub fn open(path: &str) -> anyhow::Result<Self> {
+        Self::new(Database::create(path)?)
+    }
+
+    pub(super) fn new(db: Database) -> anyhow::Result<Self> {
+        // create the tables, so reads don't fail before the first block is indexed
+        let txn = db.begin_write()?;
+        txn.open_table(ADDRESS_TRANSACTIONS)?;
+        txn.open_table(META)?;
+        txn.commit()?;
+
+        Ok(Self { db: Arc::new(db) })
+    }
+
+    /// The last indexed block, `None` if no block was indexed yet
+    pub fn last_block(&self) -> anyhow::Result<Option<u64>> {
+        let txn = self.db.begin_read()?;
+        let meta = txn.open_table(META)?;
+        Ok(meta.get(LAST_BLOCK)?.map(|v| v.value()))
+    }
+
+    /// Index the transactions of a block under their sender and receiver, blocks must be
+    /// indexed in order, blocks that are already indexed are skipped.
+    pub fn index_block(
+        &self,
+        block_number: u64,
+        transactions: &[IndexedTransaction],
+    ) -> anyhow::Result<()> {
+        let txn = self.db.begin_write()?;
+        {
+            let mut meta = txn.open_table(META)?;
+            let last_block = meta.get(LAST_BLOCK)?.map(|v| v.value());
+            if last_block.is_some_and(|last| last >= block_number) {
+                return Ok(());
+            }
+
+            let mut table = txn.open_table(ADDRESS_TRANSACTIONS)?;
+            for tx in transactions {
+                let value = tx.encode();
+                table.insert(transaction_key(&tx.from, tx.position), value)?;
+                if let Some(to) = tx.to.filter(|to| *to != tx.from) {
+                    table.insert(transaction_key(&to, tx.position), value)?;
+                }
+            }
+            meta.insert(LAST_BLOCK, block_number)?;
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Transactions sent or received by `address`, newest first, starting before `before`
+    pub fn transactions(
+        &self,
+        address: &Address,
+        before: Option<TransactionPosition>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<IndexedTransaction>> {
+        let start = transaction_key(
+            address,
+            TransactionPosition {
+                block_number: 0,
+                transaction_index: 0,
+            },
+        );
+        let txn = self.db.begin_read()?;
+        let table = txn.open_table(ADDRESS_TRANSACTIONS)?;
+
+        let range = match before {
+            Some(before) => table.range(start..transaction_key(address, before))?,
+            None => table.range(start..=transaction_key(address, TransactionPosition::MAX))?,
+        };
+
+        let mut transactions = Vec::new();
+        for entry in range.rev().take(limit) {
+            let (key, value) = entry?;
+            transactions.push(IndexedTransaction::decode(&key.value(), &value.value()));
+        }
+        Ok(transactions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use redb::backends::InMemoryBackend;
+
+    fn tx(hash: u8, block_number: u64, from: Address, to: Option<Address>) -> IndexedTransaction {
+        IndexedTransaction {
+            hash: B256::repeat_byte(hash),
+            position: TransactionPosition {
+                block_number,
+                transaction_index: hash as u32,
+            },
+            from,
+            to,
+        }
+    }
+
+    #[test]
+    fn test_history_store() {
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap();
+        let store = HistoryStore::new(db).unwrap();
+        let alice = Address::repeat_byte(0x11);
+        let bob = Address::repeat_byte(0x22);
+        let carol = Address::repeat_byte(0x33);
+
+        assert_eq!(store.last_block().unwrap(), None);
+        assert!(store.transactions(&alice, None, 10).unwrap().is_empty());
+
+        store
+            .index_block(1, &[tx(1, 1, alice, Some(bob)), tx(2, 1, bob, Some(carol))])
+            .unwrap();
+        store.index_block(2, &[tx(3, 2, alice, None)]).unwrap();
+        store
+            .index_block(3, &[tx(4, 3, carol, Some(alice))])
+            .unwrap();
+        assert_eq!(store.last_block().unwrap(), Some(3));
+
+        // Newest first
+        let hashes =
+            |txs: Vec<IndexedTransaction>| txs.iter().map(|tx| tx.hash[0]).collect::<Vec<_>>();
+        assert_eq!(
+            hashes(store.transactions(&alice, None, 10).unwrap()),
+            vec![4, 3, 1]
+        );
+        assert_eq!(
+            hashes(store.transactions(&bob, None, 10).unwrap()),
+            vec![2, 1]
+        );
+        assert_eq!(
+            hashes(store.transactions(&carol, None, 10).unwrap()),
+            vec![4, 2]
+        );
+
+        // Pages
+        let page = store.transactions(&alice, None, 2).unwrap();
+        assert_eq!(hashes(page.clone()), vec![4, 3]);
+        let page = store
+            .transactions(&alice, Some(page[1].position), 2)
+            .unwrap();
+        assert_eq!(page, vec![tx(1, 1, alice, Some(bob))]);
+
+        // Blocks are only indexed once
+        store.index_block(2, &[tx(5, 2, alice, None)]).unwrap();
+        assert_eq!(store.transactions(&alice, None, 10).unwrap().len(), 3);
+
+        // Self transfers are listed once
+        store.index_block(4, &[tx(6, 4, bob, Some(bob))]).unwrap();
+        assert_eq!(
+            hashes(store.transactions(&bob, None, 10).unwrap()),
+            vec![6, 2, 1]
+        );
+    }
+}
@@ -1,18 +1,36 @@
 mod auth;
 mod config;
+mod history;
+mod metrics;
 mod proxy;
 mod service;
+#[cfg(test)]
+mod test_utils;
 
 #[macro_use]
 extern crate tracing;
 
-use auth::{AuthenticationMiddleware, JwtSigner, SiweAuthRpcImpl, SiweAuthRpcServer};
-use dashmap::DashSet;
+use crate::config::{CliArgs, Command};
+use auth::{
+    API_KEY_SIZE, AccessPolicy, AccessPolicyMiddleware, ApiKeyMethods, ApiKeyScopeMiddleware,
+    ApiKeys, AuthenticationMiddleware, InMemoryRevocationList, JwtSigner, SessionMiddleware,
+    SharedRevocationList, SiweAuthRpcImpl, SiweAuthRpcServer, WsAuthRpcImpl, WsAuthRpcServer,
+    WsSessions,
+};
+use clap::Parser;
+use history::{HistoryIndexer, HistoryRpcImpl, HistoryRpcServer, HistoryStore};
 use jsonrpsee::core::middleware::RpcServiceBuilder;
-use jsonrpsee::{Methods, RpcModule, server::Server};
-use proxy::{EthRpcProxyServer, RpcProxyImpl, ScrollRpcProxyServer};
+use jsonrpsee::server::{Server, ServerHandle};
+use jsonrpsee::{Methods, RpcModule};
+use proxy::{
+    CallAllowlist, EthPubSubProxyServer, EthRpcProxyServer, PassthroughMethodConfig,
+    PubSubProxyImpl, RpcProxyImpl, ScrollRpcProxyServer, TxWhitelist, passthrough_module,
+};
+use rand::distr::{Alphanumeric, SampleString};
 use std::iter::once;
 use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
 use tower::ServiceBuilder;
 use tower_http::auth::AsyncRequireAuthorizationLayer;
 use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
@@ -20,35 +38,135 @@ use tower_http::sensitive_headers::SetSensitiveRequestHeadersLayer;
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::EnvFilter;
 
-use service::{RpcLoggerMiddleware, log_request};
+use metrics::{METRICS_CONTENT_TYPE, metrics};
+use service::{
+    BatchLimitMiddleware, HttpRoutesLayer, ReadinessCheck, RpcLoggerMiddleware,
+    RpcMetricsMiddleware, liveness_route, log_request,
+};
 
 fn all_apis(
-    jwt: JwtSigner,
-    jwt_expiry_secs: usize,
-    validium_url: &str,
-    withdraw_proofs_url: &str,
+    auth_server: SiweAuthRpcImpl,
+    proxy_server: RpcProxyImpl,
+    passthrough_methods: &[PassthroughMethodConfig],
+    ws_auth: WsAuthRpcImpl,
+    pubsub: Option<PubSubProxyImpl>,
+    history: Option<HistoryRpcImpl>,
+    policy: &AccessPolicy,
 ) -> anyhow::Result<impl Into<Methods>> {
-    let auth_server = SiweAuthRpcImpl::new(jwt, jwt_expiry_secs);
-    let eth_proxy_server = RpcProxyImpl::new(validium_url, withdraw_proofs_url)?;
-    let scroll_proxy_server = RpcProxyImpl::new(validium_url, withdraw_proofs_url)?;
-
     let mut module = RpcModule::new(());
     module.merge(SiweAuthRpcServer::into_rpc(auth_server))?;
-    module.merge(EthRpcProxyServer::into_rpc(eth_proxy_server))?;
-    module.merge(ScrollRpcProxyServer::into_rpc(scroll_proxy_server))?;
+    module.merge(WsAuthRpcServer::into_rpc(ws_auth))?;
+    module.merge(EthRpcProxyServer::into_rpc(proxy_server.clone()))?;
+    module.merge(passthrough_module(
+        proxy_server.validium_client(),
+        passthrough_methods,
+        policy,
+    )?)?;
+    module.merge(ScrollRpcProxyServer::into_rpc(proxy_server))?;
+    if let Some(pubsub) = pubsub {
+        module.merge(EthPubSubProxyServer::into_rpc(pubsub))?;
+    }
+    if let Some(history) = history {
+        module.merge(HistoryRpcServer::into_rpc(history))?;
+    }
+
+    // Every method must have an access rule
+    policy.check_methods(module.method_names())?;
     Ok(module)
 }
 
-pub async fn run_server() -> anyhow::Result<SocketAddr> {
-    let cfg = config::load_config()?;
+pub async fn run_server(args: CliArgs) -> anyhow::Result<SocketAddr> {
+    let cfg = config::load_config(args)?;
     let jwt = JwtSigner::from_config(cfg.jwt_signer_keys.as_slice(), &cfg.default_kid)?;
 
-    // Only load admin_keys from config file
-    let admin_keys = DashSet::default();
-    for key in cfg.admin_keys {
-        admin_keys.insert(key);
+    // Only load admin_keys and api_keys from config file
+    let api_keys = ApiKeys::from_config(&cfg.admin_keys, &cfg.api_keys)?;
+    debug!(
+        "Loaded {} admin keys and {} API keys",
+        cfg.admin_keys.len(),
+        cfg.api_keys.len()
+    );
+
+    // Shared by the middleware and `siwe_signOut`
+    let revoked_tokens: SharedRevocationList = Arc::new(InMemoryRevocationList::new());
+
+    let tx_whitelist = TxWhitelist::from_config(&cfg.tx_whitelist);
+    debug!("Loaded {} tx whitelist entries", cfg.tx_whitelist.len());
+
+    let call_allowlist = CallAllowlist::from_config(&cfg.call_allowlist)?;
+    debug!("Loaded {} call allowlist entries", cfg.call_allowlist.len());
+
+    let policy = AccessPolicy::default()
+        .with_methods(
+            cfg.passthrough_methods
+                .iter()
+                .map(|m| (m.method.as_str(), m.access)),
+        )?
+        .with_file(cfg.access_policy_file.as_deref())?;
+    let policy = Arc::new(policy);
+    debug!(
+        "Loaded {} pass-through methods",
+        cfg.passthrough_methods.len()
+    );
+
+    let authenticator = AuthenticationMiddleware::new(
+        jwt.clone(),
+        api_keys,
+        revoked_tokens.clone(),
+        cfg.reject_invalid_tokens,
+    );
+
+    // Sessions of WebSocket connections authenticated with `ws_authenticate`
+    let ws_sessions = WsSessions::new();
+    let pubsub = cfg.validium_ws_url.as_deref().map(|url| {
+        PubSubProxyImpl::new(
+            url,
+            ws_sessions.clone(),
+            revoked_tokens.clone(),
+            policy.clone(),
+        )
+    });
+
+    let proxy_server = RpcProxyImpl::new(
+        &cfg.validium_url,
+        &cfg.withdraw_proofs_url,
+        tx_whitelist,
+        call_allowlist,
+        policy.clone(),
+        cfg.max_logs_block_range,
+        cfg.redact_receipt_logs,
+        cfg.transfer_events.iter().copied().collect(),
+    )?;
+
+    // Only accept SIWE messages signed for the chain we are proxying to. A mismatch or an
+    // unreachable upstream doesn't stop the server, the readiness probe checks it again.
+    match cfg.siwe.chain_id {
+        Some(expected) => match proxy_server.validium_chain_id().await {
+            Ok(chain_id) if chain_id != expected => warn!(
+                "siwe.chain_id {expected} does not match validium chain id {chain_id}, \
+                 not ready until fixed"
+            ),
+            Ok(_) => {}
+            Err(e) => warn!("Could not check siwe.chain_id: {e}"),
+        },
+        None => warn!("siwe.chain_id is not set, SIWE messages of any chain are accepted"),
+    }
+    if cfg.siwe.domains.is_empty() {
+        warn!("siwe.domains is not set, SIWE messages of any domain are accepted");
     }
-    debug!("Loaded {} admin keys", admin_keys.len());
+
+    // Set to not ready when shutting down
+    let readiness = ReadinessCheck::new(proxy_server.clone(), jwt.clone(), cfg.siwe.chain_id);
+
+    // Public keys for verifying user JWTs offline
+    let http_routes = HttpRoutesLayer::new()
+        .json(
+            "/.well-known/jwks.json",
+            serde_json::to_string(&jwt.jwks())?,
+        )
+        // Probes, served without authorization like the routes above
+        .route("/health", liveness_route())
+        .route("/ready", readiness.clone().route());
 
     let http_middleware = ServiceBuilder::new()
         .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
@@ -57,11 +175,33 @@ pub async fn run_server() -> anyhow::Result<SocketAddr> {
             hyper::header::AUTHORIZATION,
         )))
         .layer(TraceLayer::new_for_http().make_span_with(log_request))
-        .layer(AsyncRequireAuthorizationLayer::new(
-            AuthenticationMiddleware::new(jwt.clone(), admin_keys),
-        ));
+        .layer(http_routes)
+        .layer(AsyncRequireAuthorizationLayer::new(authenticator.clone()));
 
-    let rpc_middleware = RpcServiceBuilder::new().layer_fn(RpcLoggerMiddleware::new);
+    // Pass-through methods not marked read-only are not available to read-only API keys
+    let api_key_methods = ApiKeyMethods::new(
+        cfg.passthrough_methods
+            .iter()
+            .filter(|m| !m.read_only)
+            .map(|m| m.method.as_str()),
+    );
+
+    let rpc_middleware = RpcServiceBuilder::new()
+        .layer_fn({
+            let ws_sessions = ws_sessions.clone();
+            move |service| SessionMiddleware::new(service, ws_sessions.clone())
+        })
+        .layer_fn({
+            let policy = policy.clone();
+            move |service| RpcMetricsMiddleware::new(service, policy.clone())
+        })
+        .layer_fn(RpcLoggerMiddleware::new)
+        .layer_fn(move |service| BatchLimitMiddleware::new(service, cfg.max_batch_size))
+        .layer_fn(move |service| ApiKeyScopeMiddleware::new(service, api_key_methods.clone()))
+        .layer_fn({
+            let policy = policy.clone();
+            move |service| AccessPolicyMiddleware::new(service, policy.clone())
+        });
 
     let server = Server::builder()
         .set_http_middleware(http_middleware)
@@ -73,24 +213,138 @@ pub async fn run_server() -> anyhow::Result<SocketAddr> {
     info!("Server is listening on {addr}");
     info!("Validium endpoint is {}", cfg.validium_url);
     info!("Withdraw proofs endpoint is {}", cfg.withdraw_proofs_url);
+    if let Some(url) = &cfg.validium_ws_url {
+        info!("Validium WebSocket endpoint is {url}");
+    }
 
-    let handle = server.start(all_apis(
+    // Metrics for Prometheus, on their own listener so that they are not public
+    let metrics_server = match &cfg.metrics_address {
+        Some(address) => Some(serve_metrics(address).await?),
+        None => None,
+    };
+
+    // Index the transactions of each address in the background
+    let history = match &cfg.history {
+        Some(history_cfg) => {
+            let store = HistoryStore::open(&history_cfg.path)?;
+            info!("Transaction history index is {}", history_cfg.path);
+            let indexer =
+                HistoryIndexer::new(proxy_server.validium_client(), store.clone(), history_cfg);
+            tokio::spawn(indexer.run());
+            Some(HistoryRpcImpl::new(store))
+        }
+        None => None,
+    };
+
+    let auth_server = SiweAuthRpcImpl::new(
         jwt,
         cfg.jwt_expiry_secs,
-        &cfg.validium_url,
-        &cfg.withdraw_proofs_url,
+        cfg.refresh_token_expiry_secs,
+        revoked_tokens,
+        cfg.siwe,
+        proxy_server.validium_client(),
+    );
+
+    let handle = server.start(all_apis(
+        auth_server,
+        proxy_server,
+        &cfg.passthrough_methods,
+        WsAuthRpcImpl::new(authenticator, ws_sessions),
+        pubsub,
+        history,
+        &policy,
     )?);
-    handle.stopped().await;
+
+    tokio::select! {
+        _ = handle.clone().stopped() => {}
+        signal = wait_for_shutdown_signal() => {
+            info!("Received {signal}, shutting down");
+            readiness.set_draining();
+            // give load balancers time to notice, before refusing connections
+            tokio::time::sleep(Duration::from_secs(cfg.shutdown_delay_secs)).await;
+
+            // stop accepting connections, in-flight requests are completed
+            handle.stop()?;
+            let timeout = Duration::from_secs(cfg.shutdown_timeout_secs);
+            if tokio::time::timeout(timeout, handle.stopped()).await.is_err() {
+                warn!(
+                    "In-flight requests not completed after {}s, stopping",
+                    cfg.shutdown_timeout_secs
+                );
+            }
+        }
+    }
+
+    if let Some(metrics_server) = metrics_server {
+        let _ = metrics_server.stop();
+    }
     Ok(addr)
 }
 
+/// Serve the Prometheus metrics on `GET /metrics` of `address`
+async fn serve_metrics(address: &str) -> anyhow::Result<ServerHandle> {
+    let routes =
+        HttpRoutesLayer::new().render("/metrics", METRICS_CONTENT_TYPE, || metrics().render());
+    let server = Server::builder()
+        .set_http_middleware(ServiceBuilder::new().layer(routes))
+        .build(address.parse::<SocketAddr>()?)
+        .await?;
+
+    info!("Metrics are served on {}/metrics", server.local_addr()?);
+    Ok(server.start(RpcModule::new(())))
+}
+
+/// Wait for a shutdown signal, returns its name. If the signal handlers can't be
+/// installed this never resolves, the server keeps running without graceful shutdown.
+async fn wait_for_shutdown_signal() -> &'static str {
+    match shutdown_signal().await {
+        Ok(signal) => signal,
+        Err(e) => {
+            warn!("Could not listen for shutdown signals: {e}");
+            std::future::pending().await
+        }
+    }
+}
+
+/// Wait for SIGTERM or SIGINT, returns the name of the signal
+#[cfg(unix)]
+async fn shutdown_signal() -> anyhow::Result<&'static str> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        _ = terminate.recv() => Ok("SIGTERM"),
+        result = tokio::signal::ctrl_c() => result.map(|_| "SIGINT").map_err(Into::into),
+    }
+}
+
+/// Wait for Ctrl-C, there is no SIGTERM on other platforms
+#[cfg(not(unix))]
+async fn shutdown_signal() -> anyhow::Result<&'static str> {
+    tokio::signal::ctrl_c().await?;
+    Ok("Ctrl-C")
+}
+
+/// Print a key and its hash for the config file
+fn hash_key(key: Option<String>) {
+    let key = key.unwrap_or_else(|| Alphanumeric.sample_string(&mut rand::rng(), API_KEY_SIZE));
+    println!("key:  {key}");
+    println!("hash: {}", auth::hash_api_key(&key));
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt()
         .with_env_filter(EnvFilter::from_default_env())
         .init();
 
-    match run_server().await {
+    let args = CliArgs::parse();
+    if let Some(Command::HashKey { key }) = args.command {
+        hash_key(key);
+        return Ok(());
+    }
+
+    match run_server(args).await {
         Ok(_) => Ok(()),
         Err(err) => {
             error!("Error starting server: {}", err);
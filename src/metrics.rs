@@ -0,0 +1,182 @@
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+use prometheus::{
+    HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+
+use crate::auth::AccessLevel;
+
+/// Content type of the Prometheus text format
+pub const METRICS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+static METRICS: LazyLock<Metrics> =
+    LazyLock::new(|| Metrics::new().expect("valid metric definitions"));
+
+/// The metrics of the server, exposed on `/metrics`
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+pub struct Metrics {
+    registry: Registry,
+    rpc_requests: IntCounterVec,
+    rpc_duration: HistogramVec,
+    auth_requests: IntCounterVec,
+    siwe_sign_ins: IntCounterVec,
+    siwe_nonces: IntGauge,
+    upstream_duration: HistogramVec,
+}
+
+impl Metrics {
+    fn new() -> prometheus::Result<Self> {
+        let registry = Registry::new_custom(Some("rpc_proxy".to_string()), None)?;
+
+        let rpc_requests = IntCounterVec::new(
+            Opts::new(
+                "rpc_requests_total",
+                "RPC calls by method and result, code is the JSON-RPC error code or \"ok\"",
+            ),
+            &["method", "code"],
+        )?;
+        let rpc_duration = HistogramVec::new(
+            HistogramOpts::new("rpc_request_duration_seconds", "Duration of RPC calls"),
+            &["method"],
+        )?;
+        let auth_requests = IntCounterVec::new(
+            Opts::new(
+                "auth_requests_total",
+                "Authenticated HTTP requests and WebSocket connections by access level",
+            ),
+            &["access", "outcome"],
+        )?;
+        let siwe_sign_ins = IntCounterVec::new(
+            Opts::new("siwe_sign_ins_total", "SIWE sign-ins by outcome"),
+            &["outcome"],
+        )?;
+        let siwe_nonces = IntGauge::new("siwe_nonces", "SIWE nonces issued and not used yet")?;
+        let upstream_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "upstream_request_duration_seconds",
+                "Duration of calls to the upstreams",
+            ),
+            &["upstream", "method"],
+        )?;
+
+        registry.register(Box::new(rpc_requests.clone()))?;
+        registry.register(Box::new(rpc_duration.clone()))?;
+        registry.register(Box::new(auth_requests.clone()))?;
+        registry.register(Box::new(siwe_sign_ins.clone()))?;
+        registry.register(Box::new(siwe_nonces.clone()))?;
+        registry.register(Box::new(upstream_duration.clone()))?;
+
+        Ok(Self {
+            registry,
+            rpc_requests,
+            rpc_duration,
+            auth_requests,
+            siwe_sign_ins,
+            siwe_nonces,
+            upstream_duration,
+        })
+    }
+
+    /// Record an RPC call and its JSON-RPC error code, methods that are not registered (`None`)
+    /// are counted together as "unknown" so that clients can't add labels
+    pub fn record_rpc(&self, method: Option<&str>, code: Option<i32>, duration: Duration) {
+        let method = method.unwrap_or("unknown");
+        let code = code.map_or_else(|| "ok".to_string(), |code| code.to_string());
+
+        self.rpc_requests
+            .with_label_values(&[method, code.as_str()])
+            .inc();
+        self.rpc_duration
+            .with_label_values(&[method])
+            .observe(duration.as_secs_f64());
+    }
+
+    /// Record the authentication of a request, invalid tokens count as `none`
+    pub fn record_auth<E>(&self, result: Result<&AccessLevel, E>) {
+        let (access, outcome) = match result {
+            Ok(access) => (access_kind(access), "ok"),
+            Err(_) => ("none", "invalid"),
+        };
+        self.auth_requests
+            .with_label_values(&[access, outcome])
+            .inc();
+    }
+
+    pub fn record_sign_in(&self, success: bool) {
+        let outcome = if success { "success" } else { "failure" };
+        self.siwe_sign_ins.with_label_values(&[outcome]).inc();
+    }
+
+    pub fn set_siwe_nonces(&self, count: u64) {
+        self.siwe_nonces.set(count as i64);
+    }
+
+    /// Time a call to an upstream, e.g. "validium" or "withdraw_proofs"
+    pub async fn time_upstream<F: Future>(
+        &self,
+        upstream: &str,
+        method: &str,
+        call: F,
+    ) -> F::Output {
+        let started = Instant::now();
+        let output = call.await;
+        self.upstream_duration
+            .with_label_values(&[upstream, method])
+            .observe(started.elapsed().as_secs_f64());
+        output
+    }
+
+    /// All metrics in the Prometheus text format
+    pub fn render(&self) -> String {
+        TextEncoder::new()
+            .encode_to_string(&self.registry.gather())
+            .unwrap_or_else(|e| {
+                error!("Failed to encode metrics: {e}");
+                String::new()
+            })
+    }
+}
+
+fn access_kind(access: &AccessLevel) -> &'static str {
+    match access {
+        AccessLevel::None => "none",
+        AccessLevel::Basic(_) => "basic",
+        AccessLevel::Scoped(_) => "scoped",
+        AccessLevel::Full => "full",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Address;
+    use jsonrpsee::types::error::{INVALID_REQUEST_CODE, METHOD_NOT_FOUND_CODE};
+
+    #[test]
+    fn test_render_metrics() {
+        let metrics = Metrics::new().unwrap();
+        let ms = Duration::from_millis(5);
+        metrics.record_rpc(None, Some(METHOD_NOT_FOUND_CODE), ms);
+        metrics.record_rpc(None, Some(INVALID_REQUEST_CODE), ms);
+        metrics.record_rpc(Some("eth_chainId"), None, ms);
+        metrics.record_auth::<()>(Ok(&AccessLevel::Basic(Address::ZERO)));
+        metrics.record_auth(Err(()));
+        metrics.record_sign_in(false);
+        metrics.set_siwe_nonces(3);
+
+        let text = metrics.render();
+        assert!(text.contains(r#"rpc_proxy_rpc_requests_total{code="-32601",method="unknown"} 1"#));
+        assert!(text.contains(r#"rpc_proxy_rpc_requests_total{code="-32600",method="unknown"} 1"#));
+        assert!(text.contains(r#"rpc_proxy_rpc_requests_total{code="ok",method="eth_chainId"} 1"#));
+        assert!(text.contains(r#"rpc_proxy_auth_requests_total{access="basic",outcome="ok"} 1"#));
+        assert!(
+            text.contains(r#"rpc_proxy_auth_requests_total{access="none",outcome="invalid"} 1"#)
+        );
+        assert!(text.contains(r#"rpc_proxy_siwe_sign_ins_total{outcome="failure"} 1"#));
+        assert!(text.contains("rpc_proxy_siwe_nonces 3"));
+    }
+}
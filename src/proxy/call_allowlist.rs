@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use alloy::primitives::{Address, B256, Selector, keccak256};
+use jsonrpsee::core::RpcResult;
+use serde::Deserialize;
+
+use super::error::{call_not_allowed, not_whitelisted};
+use super::visibility::topic_address;
+use crate::auth::AccessLevel;
+
+/// Constraint on an argument of an allowlisted function
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CallArg {
+    /// An address the caller may access, e.g. the owner of `balanceOf(address)`
+    #[serde(rename = "self")]
+    SelfAddress,
+    /// Any value
+    Any,
+}
+
+/// Configuration for a read-only function that basic users may call with `eth_call`
+#[derive(Debug, Deserialize)]
+pub struct CallAllowlistEntryConfig {
+    pub to: Address,
+    /// Function signature, e.g. "balanceOf(address)", only static argument types are supported
+    pub function: String,
+    /// Constraint of each argument, e.g. ["self", "any"] for `allowance(address,address)`
+    pub args: Vec<CallArg>,
+}
+
+/// Parse the argument types of a function signature, `None` if it is malformed
+fn parse_arg_types(signature: &str) -> Option<Vec<&str>> {
+    let (name, args) = signature.strip_suffix(')')?.split_once('(')?;
+    if name.is_empty() || args.contains(['(', ')', ' ']) {
+        return None;
+    }
+    Some(args.split(',').filter(|arg| !arg.is_empty()).collect())
+}
+
+/// Whether values of the ABI type are encoded in place as a single 32 byte word
+fn is_static_word(ty: &str) -> bool {
+    let bits = |n: &str| {
+        n.parse::<u32>()
+            .is_ok_and(|n| n > 0 && n <= 256 && n % 8 == 0)
+    };
+    match ty {
+        "address" | "bool" | "uint" | "int" => true,
+        _ => {
+            if let Some(n) = ty.strip_prefix("uint").or_else(|| ty.strip_prefix("int")) {
+                bits(n)
+            } else if let Some(n) = ty.strip_prefix("bytes") {
+                n.parse::<u32>().is_ok_and(|n| (1..=32).contains(&n))
+            } else {
+                false
+            }
+        }
+    }
+}
+
+/// The read-only (contract, function) pairs that basic users may call, with argument constraints
+#[derive(Clone, Default)]
+pub struct CallAllowlist {
+    entries: Arc<HashMap<(Address, Selector), Vec<CallArg>>>,
+}
+
+impl CallAllowlist {
+    pub fn from_config(entries: &[CallAllowlistEntryConfig]) -> anyhow::Result<Self> {
+        let mut map = HashMap::new();
+        for entry in entries {
+            let types = parse_arg_types(&entry.function).ok_or_else(|| {
+                anyhow::anyhow!("call allowlist: invalid function {}", entry.function)
+            })?;
+            if let Some(ty) = types.iter().find(|ty| !is_static_word(ty)) {
+                anyhow::bail!(
+                    "call allowlist: {}: argument type {ty} is not supported",
+                    entry.function
+                );
+            }
+            if types.len() != entry.args.len() {
+                anyhow::bail!(
+                    "call allowlist: {}: expected {} args, got {}",
+                    entry.function,
+                    types.len(),
+                    entry.args.len()
+                );
+            }
+            for (ty, arg) in types.iter().zip(&entry.args) {
+                if *arg == CallArg::SelfAddress && *ty != "address" {
+                    anyhow::bail!(
+                        "call allowlist: {}: only address arguments can be \"self\"",
+                        entry.function
+                    );
+                }
+            }
+
+            let selector = Selector::from_slice(&keccak256(&entry.function)[..4]);
+            if map
+                .insert((entry.to, selector), entry.args.clone())
+                .is_some()
+            {
+                anyhow::bail!(
+                    "call allowlist: {} on {} is configured more than once",
+                    entry.function,
+                    entry.to
+                );
+            }
+        }
+
+        Ok(Self {
+            entries: Arc::new(map),
+        })
+    }
+
+    /// Check whether the user may call contract `to` with `input`
+    pub fn check(&self, access: &AccessLevel, to: &Address, input: &[u8]) -> RpcResult<()> {
+        let Some(selector) = input.get(..4).map(Selector::from_slice) else {
+            return Err(call_not_allowed("calldata too short for function selector"));
+        };
+        let Some(args) = self.entries.get(&(*to, selector)) else {
+            return Err(not_whitelisted(to, &selector));
+        };
+
+        // static arguments only, so the calldata is one word per argument
+        let data = &input[4..];
+        if data.len() != 32 * args.len() {
+            return Err(call_not_allowed("unexpected calldata length"));
+        }
+
+        for (arg, word) in args.iter().zip(data.chunks(32)) {
+            let authorized = match arg {
+                CallArg::Any => true,
+                CallArg::SelfAddress => {
+                    topic_address(&B256::from_slice(word)).is_some_and(|a| access.is_authorized(&a))
+                }
+            };
+            if !authorized {
+                return Err(call_not_allowed("address argument is not accessible"));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{U256, address};
+
+    fn entry(function: &str, args: Vec<CallArg>) -> CallAllowlistEntryConfig {
+        CallAllowlistEntryConfig {
+            to: address!("0x1234567890abcdef1234567890abcdef12345678"),
+            function: function.to_string(),
+            args,
+        }
+    }
+
+    fn calldata(function: &str, words: &[B256]) -> Vec<u8> {
+        let mut data = keccak256(function)[..4].to_vec();
+        for word in words {
+            data.extend_from_slice(word.as_slice());
+        }
+        data
+    }
+
+    #[test]
+    fn test_call_allowlist_check() {
+        let token = address!("0x1234567890abcdef1234567890abcdef12345678");
+        let user = Address::repeat_byte(0x11);
+        let other = Address::repeat_byte(0x22);
+        let access = AccessLevel::Basic(user);
+
+        let allowlist = CallAllowlist::from_config(&[
+            entry("balanceOf(address)", vec![CallArg::SelfAddress]),
+            entry(
+                "allowance(address,address)",
+                vec![CallArg::SelfAddress, CallArg::Any],
+            ),
+            entry("totalSupply()", vec![]),
+        ])
+        .unwrap();
+
+        let balance_of = |address: Address| calldata("balanceOf(address)", &[address.into_word()]);
+        assert!(allowlist.check(&access, &token, &balance_of(user)).is_ok());
+        assert!(
+            allowlist
+                .check(&access, &token, &balance_of(other))
+                .is_err()
+        );
+        assert!(
+            allowlist
+                .check(&AccessLevel::Full, &token, &balance_of(other))
+                .is_ok()
+        );
+
+        let allowance = |owner: Address, spender: Address| {
+            calldata(
+                "allowance(address,address)",
+                &[owner.into_word(), spender.into_word()],
+            )
+        };
+        assert!(
+            allowlist
+                .check(&access, &token, &allowance(user, other))
+                .is_ok()
+        );
+        assert!(
+            allowlist
+                .check(&access, &token, &allowance(other, user))
+                .is_err()
+        );
+
+        assert!(
+            allowlist
+                .check(&access, &token, &calldata("totalSupply()", &[]))
+                .is_ok()
+        );
+
+        // Not an address
+        let word = B256::from(U256::MAX);
+        let data = calldata("balanceOf(address)", &[word]);
+        assert!(allowlist.check(&access, &token, &data).is_err());
+        // Extra calldata
+        let mut data = balance_of(user);
+        data.extend_from_slice(&[0; 32]);
+        assert!(allowlist.check(&access, &token, &data).is_err());
+        // Other contract, other function, no selector
+        assert!(allowlist.check(&access, &other, &balance_of(user)).is_err());
+        let data = calldata("transfer(address,uint256)", &[user.into_word(), B256::ZERO]);
+        assert!(allowlist.check(&access, &token, &data).is_err());
+        assert!(allowlist.check(&access, &token, &[0x70]).is_err());
+    }
+
+    #[test]
+    fn test_call_allowlist_config() {
+        assert!(CallAllowlist::from_config(&[entry("balanceOf", vec![])]).is_err());
+        assert!(CallAllowlist::from_config(&[entry("balanceOf(address)", vec![])]).is_err());
+        assert!(CallAllowlist::from_config(&[entry("name(string)", vec![CallArg::Any])]).is_err());
+        assert!(
+            CallAllowlist::from_config(&[entry("get(uint256)", vec![CallArg::SelfAddress])])
+                .is_err()
+        );
+        assert!(
+            CallAllowlist::from_config(&[
+                entry("balanceOf(address)", vec![CallArg::SelfAddress]),
+                entry("balanceOf(address)", vec![CallArg::Any]),
+            ])
+            .is_err()
+        );
+        assert!(
+            CallAllowlist::from_config(&[entry(
+                "get(uint8,int256,bytes32,bool)",
+                vec![CallArg::Any; 4]
+            )])
+            .is_ok()
+        );
+    }
+}
@@ -1,9 +1,29 @@
+use alloy::primitives::{Address, Selector};
 use jsonrpsee::core::ClientError;
 use jsonrpsee::types::ErrorObjectOwned;
-use jsonrpsee::types::error::{INTERNAL_ERROR_CODE, INTERNAL_ERROR_MSG, INVALID_REQUEST_CODE};
+use jsonrpsee::types::error::{
+    INTERNAL_ERROR_CODE, INTERNAL_ERROR_MSG, INVALID_PARAMS_CODE, INVALID_PARAMS_MSG,
+    INVALID_REQUEST_CODE,
+};
 
-pub fn unauthorized() -> ErrorObjectOwned {
-    ErrorObjectOwned::owned(INVALID_REQUEST_CODE, "unauthorized", Some("unauthorized"))
+pub use crate::auth::unauthorized;
+
+pub fn not_whitelisted(to: &Address, selector: &Selector) -> ErrorObjectOwned {
+    ErrorObjectOwned::owned(
+        INVALID_REQUEST_CODE,
+        "unauthorized",
+        Some(format!(
+            "contract call not whitelisted: to={to}, selector={selector}"
+        )),
+    )
+}
+
+pub fn invalid_params(details: impl AsRef<str>) -> ErrorObjectOwned {
+    ErrorObjectOwned::owned(
+        INVALID_PARAMS_CODE,
+        INVALID_PARAMS_MSG,
+        Some(details.as_ref()),
+    )
 }
 
 pub fn internal_error(msg: impl AsRef<str>) -> ErrorObjectOwned {
@@ -20,3 +40,15 @@ pub fn proxy_call_failed(e: ClientError) -> ErrorObjectOwned {
         ),
     }
 }
+
+pub fn logs_filter_not_scoped() -> ErrorObjectOwned {
+    ErrorObjectOwned::owned(
+        INVALID_REQUEST_CODE,
+        "unauthorized",
+        Some("log filter must be limited to your address by address or an indexed topic"),
+    )
+}
+
+pub fn call_not_allowed(reason: &str) -> ErrorObjectOwned {
+    ErrorObjectOwned::owned(INVALID_REQUEST_CODE, "unauthorized", Some(reason))
+}
@@ -6,7 +6,7 @@ use alloy::serde::JsonStorageKey;
 use alloy_rpc_types::{
     Block as EthBlock, BlockNumberOrTag, FeeHistory, Filter, Log, TransactionRequest,
 };
-use jsonrpsee::core::RpcResult;
+use jsonrpsee::core::{RpcResult, SubscriptionResult};
 use scroll_alloy_rpc_types::{ScrollTransactionReceipt as Receipt, Transaction};
 use serde::{Deserialize, Serialize};
 
@@ -25,6 +25,36 @@ pub struct Withdrawal {
     pub proof: Bytes,
 }
 
+impl Withdrawal {
+    /// The status of the withdrawal, claimable once its proof is available
+    pub fn status(&self) -> WithdrawalStatus {
+        if self.proof.is_empty() {
+            WithdrawalStatus::Pending
+        } else {
+            WithdrawalStatus::Claimable
+        }
+    }
+}
+
+/// Status of a withdrawal, as tracked by the withdraw-proofs service
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WithdrawalStatus {
+    /// The proof is not available yet
+    Pending,
+    /// The proof is available, the withdrawal can be claimed on L1
+    Claimable,
+}
+
+/// A page of withdrawals of an address
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WithdrawalsPage {
+    pub withdrawals: Vec<Withdrawal>,
+    /// Pass to the next call to get the next page, `None` on the last page
+    pub next_cursor: Option<String>,
+}
+
 #[rpc(server, client, namespace = "scroll")]
 pub trait ScrollRpcProxy {
     #[method(name = "getL1MessagesInBlock", with_extensions)]
@@ -40,6 +70,16 @@ pub trait ScrollRpcProxy {
     #[method(name = "withdrawalByMessageHash", with_extensions)]
     async fn withdrawal_by_message_hash(&self, message_hash: B256)
     -> RpcResult<Option<Withdrawal>>;
+
+    /// Withdrawals sent from `address`, relayed to the `scroll_withdrawalsByAddress` method of the
+    /// withdraw-proofs service, which takes the same params and returns the same page
+    #[method(name = "withdrawalsByAddress", with_extensions)]
+    async fn withdrawals_by_address(
+        &self,
+        address: Address,
+        cursor: Option<String>,
+        status: Option<WithdrawalStatus>,
+    ) -> RpcResult<WithdrawalsPage>;
 }
 
 // see https://github.com/paradigmxyz/reth/blob/main/crates/rpc/rpc-eth-api/src/core.rs
@@ -122,3 +162,19 @@ pub trait EthRpcProxy {
     #[method(name = "getLogs", with_extensions)]
     async fn logs(&self, filter: Filter) -> RpcResult<Vec<Log>>;
 }
+
+#[rpc(server, namespace = "eth")]
+pub trait EthPubSubProxy {
+    /// Subscribe to `newHeads` or `logs`, only available on WebSocket connections
+    #[subscription(
+        name = "subscribe" => "subscription",
+        unsubscribe = "unsubscribe",
+        item = serde_json::Value,
+        with_extensions
+    )]
+    async fn subscribe(
+        &self,
+        kind: String,
+        params: Option<serde_json::Value>,
+    ) -> SubscriptionResult;
+}
@@ -0,0 +1,170 @@
+use alloy::primitives::Address;
+use alloy_rpc_types::{BlockNumberOrTag, Filter, FilterBlockOption};
+use jsonrpsee::types::ErrorObjectOwned;
+
+use super::error::{invalid_params, logs_filter_not_scoped};
+use super::visibility::topic_address;
+use crate::auth::AccessLevel;
+
+/// Whether there is at least one address, and the user may access all of them
+fn only_authorized(access: &AccessLevel, addresses: impl Iterator<Item = Option<Address>>) -> bool {
+    let mut addresses = addresses.peekable();
+    addresses.peek().is_some()
+        && addresses.all(|address| address.is_some_and(|a| access.is_authorized(&a)))
+}
+
+/// Check that every log matching the filter involves an address the user may access:
+/// either all `address` entries or all values of an indexed parameter (topics 1 to 3) are
+/// accessible, e.g. the `from` or `to` topic of ERC-20/721 `Transfer` set to the caller.
+pub fn check_logs_filter(access: &AccessLevel, filter: &Filter) -> Result<(), ErrorObjectOwned> {
+    if only_authorized(access, filter.address.iter().copied().map(Some)) {
+        return Ok(());
+    }
+
+    let scoped_topic = filter.topics[1..]
+        .iter()
+        .any(|topic| only_authorized(access, topic.iter().map(topic_address)));
+    if scoped_topic {
+        return Ok(());
+    }
+
+    Err(logs_filter_not_scoped())
+}
+
+/// The inclusive block range of a filter, `head` resolves `latest` and missing bounds.
+/// Other tags (e.g. `safe` or `pending`) are rejected, their block is not known here.
+fn resolve_block_range(
+    from: Option<BlockNumberOrTag>,
+    to: Option<BlockNumberOrTag>,
+    head: u64,
+) -> Result<(u64, u64), ErrorObjectOwned> {
+    let resolve = |block: Option<BlockNumberOrTag>| match block {
+        Some(BlockNumberOrTag::Number(n)) => Ok(n),
+        Some(BlockNumberOrTag::Earliest) => Ok(0),
+        None | Some(BlockNumberOrTag::Latest) => Ok(head),
+        Some(tag) => Err(invalid_params(format!(
+            "block tag {tag} is not supported, use a block number or latest"
+        ))),
+    };
+    Ok((resolve(from)?, resolve(to)?))
+}
+
+/// Whether resolving the block range of the filter needs the current block number
+pub fn needs_head(filter: &Filter) -> bool {
+    match filter.block_option {
+        FilterBlockOption::AtBlockHash(_) => false,
+        FilterBlockOption::Range {
+            from_block,
+            to_block,
+        } => [from_block, to_block]
+            .iter()
+            .any(|b| matches!(b, None | Some(BlockNumberOrTag::Latest))),
+    }
+}
+
+/// Check the number of blocks searched by the filter, a block hash filter searches one block
+pub fn check_block_range(
+    filter: &Filter,
+    head: u64,
+    max_range: u64,
+) -> Result<(), ErrorObjectOwned> {
+    let FilterBlockOption::Range {
+        from_block,
+        to_block,
+    } = filter.block_option
+    else {
+        return Ok(());
+    };
+
+    let (from, to) = resolve_block_range(from_block, to_block, head)?;
+    let range = to.saturating_sub(from).saturating_add(1);
+    if range > max_range {
+        return Err(invalid_params(format!(
+            "block range of {range} blocks exceeds the limit of {max_range}"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::B256;
+
+    use crate::proxy::TRANSFER_EVENT;
+
+    #[test]
+    fn test_check_logs_filter() {
+        let user = Address::repeat_byte(0x11);
+        let other = Address::repeat_byte(0x22);
+        let access = AccessLevel::Basic(user);
+
+        // Transfers from or to the user
+        let filter = Filter::new().event_signature(TRANSFER_EVENT);
+        assert!(check_logs_filter(&access, &filter.clone().topic1(user.into_word())).is_ok());
+        assert!(check_logs_filter(&access, &filter.clone().topic2(user.into_word())).is_ok());
+        // Logs emitted by the user
+        assert!(check_logs_filter(&access, &Filter::new().address(user)).is_ok());
+
+        // Not constrained
+        assert!(check_logs_filter(&access, &filter).is_err());
+        assert!(check_logs_filter(&access, &Filter::new()).is_err());
+        // Other addresses
+        assert!(check_logs_filter(&access, &filter.clone().topic1(other.into_word())).is_err());
+        assert!(
+            check_logs_filter(
+                &access,
+                &filter
+                    .clone()
+                    .topic1(vec![user.into_word(), other.into_word()])
+            )
+            .is_err()
+        );
+        assert!(check_logs_filter(&access, &Filter::new().address(vec![user, other])).is_err());
+        // The event signature is not a parameter
+        assert!(
+            check_logs_filter(&access, &Filter::new().event_signature(user.into_word())).is_err()
+        );
+        // Not an address
+        assert!(check_logs_filter(&access, &filter.topic1(B256::repeat_byte(0x11))).is_err());
+    }
+
+    #[test]
+    fn test_check_block_range() {
+        let head = 1_000;
+
+        let filter = Filter::new().from_block(100u64).to_block(199u64);
+        assert!(!needs_head(&filter));
+        assert!(check_block_range(&filter, head, 100).is_ok());
+        assert!(check_block_range(&filter, head, 99).is_err());
+
+        // Missing bounds and latest are the current block
+        let filter = Filter::new().from_block(901u64);
+        assert!(needs_head(&filter));
+        assert!(check_block_range(&filter, head, 100).is_ok());
+        assert!(check_block_range(&Filter::new(), head, 1).is_ok());
+        let filter = Filter::new()
+            .from_block(BlockNumberOrTag::Earliest)
+            .to_block(BlockNumberOrTag::Latest);
+        assert!(check_block_range(&filter, head, 1_000).is_err());
+
+        // Other tags could resolve to any block
+        for tag in [
+            BlockNumberOrTag::Safe,
+            BlockNumberOrTag::Finalized,
+            BlockNumberOrTag::Pending,
+        ] {
+            let filter = Filter::new()
+                .from_block(tag)
+                .to_block(BlockNumberOrTag::Latest);
+            let err = check_block_range(&filter, head, 1_000).unwrap_err();
+            assert!(err.data().unwrap().get().contains("not supported"));
+            assert!(check_block_range(&Filter::new().to_block(tag), head, 1_000).is_err());
+        }
+
+        // Block hash filters search a single block
+        let filter = Filter::new().at_block_hash(B256::ZERO);
+        assert!(!needs_head(&filter));
+        assert!(check_block_range(&filter, head, 1).is_ok());
+    }
+}
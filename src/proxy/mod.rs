@@ -1,6 +1,20 @@
+mod call_allowlist;
 mod error;
 mod interface;
+mod logs;
+mod passthrough;
+mod pubsub;
+mod raw_tx;
 mod server;
+mod visibility;
+mod whitelist;
 
-pub use interface::{EthRpcProxyServer, ScrollRpcProxyServer};
+pub use call_allowlist::{CallAllowlist, CallAllowlistEntryConfig};
+pub use interface::{
+    EthPubSubProxyServer, EthRpcProxyClient, EthRpcProxyServer, ScrollRpcProxyServer,
+};
+pub use passthrough::{PassthroughMethodConfig, passthrough_module};
+pub use pubsub::PubSubProxyImpl;
 pub use server::RpcProxyImpl;
+pub use visibility::TRANSFER_EVENT;
+pub use whitelist::{TxWhitelist, TxWhitelistEntryConfig};
@@ -0,0 +1,166 @@
+use jsonrpsee::RpcModule;
+use jsonrpsee::core::RpcResult;
+use jsonrpsee::core::client::ClientT;
+use jsonrpsee::core::traits::ToRpcParams;
+use jsonrpsee::http_client::HttpClient;
+use serde::Deserialize;
+use serde_json::value::RawValue;
+
+use super::error::{invalid_params, proxy_call_failed};
+use crate::auth::{AccessPolicy, AccessRule, get_access};
+use crate::metrics::metrics;
+
+/// Configuration of a method relayed to the validium node without interpretation
+#[derive(Debug, Deserialize)]
+pub struct PassthroughMethodConfig {
+    /// Name of the RPC method, e.g. "net_version"
+    pub method: String,
+    /// Access rule of the method, same as in the access policy
+    pub access: AccessRule,
+    /// The method doesn't change state, so read-only API keys may call it
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+/// Params forwarded as received
+struct RawParams(Option<Box<RawValue>>);
+
+impl ToRpcParams for RawParams {
+    fn to_rpc_params(self) -> Result<Option<Box<RawValue>>, serde_json::Error> {
+        Ok(self.0)
+    }
+}
+
+/// Build a module relaying the configured methods to `client`, params and result are passed untouched.
+/// The rules are checked in the final `policy`, after the overrides of the policy file.
+pub fn passthrough_module(
+    client: HttpClient,
+    methods: &[PassthroughMethodConfig],
+    policy: &AccessPolicy,
+) -> anyhow::Result<RpcModule<()>> {
+    let mut module = RpcModule::new(());
+
+    for config in methods {
+        // the result is not interpreted, so rules on the result can't be checked
+        let rule = policy.rule(&config.method).unwrap_or(AccessRule::Deny);
+        if rule.checks_result() {
+            anyhow::bail!(
+                "pass-through method {}: access rule {rule} is not supported",
+                config.method
+            );
+        }
+
+        // method names live as long as the server
+        let method: &'static str = Box::leak(config.method.clone().into_boxed_str());
+        let client = client.clone();
+
+        module.register_async_method(method, move |params, _, ext| {
+            let client = client.clone();
+            async move {
+                let params = params
+                    .as_str()
+                    .map(|p| RawValue::from_string(p.to_owned()))
+                    .transpose()
+                    .map_err(|e| invalid_params(e.to_string()))?;
+
+                // check the rule here too, in case the method is reached without
+                // `AccessPolicyMiddleware`
+                rule.check_request(get_access(&ext), params.as_deref())?;
+
+                let result: RpcResult<Box<RawValue>> = metrics()
+                    .time_upstream(
+                        "validium",
+                        method,
+                        client.request(method, RawParams(params)),
+                    )
+                    .await
+                    .map_err(proxy_call_failed);
+                result
+            }
+        })?;
+    }
+
+    Ok(module)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpsee::rpc_params;
+    use serde_json::Value;
+
+    fn method(method: &str, access: AccessRule) -> PassthroughMethodConfig {
+        PassthroughMethodConfig {
+            method: method.to_string(),
+            access,
+            read_only: true,
+        }
+    }
+
+    /// The policy with the rules of `methods`
+    fn policy(methods: &[PassthroughMethodConfig]) -> AccessPolicy {
+        AccessPolicy::default()
+            .with_methods(methods.iter().map(|m| (m.method.as_str(), m.access)))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_passthrough_module() {
+        let client = HttpClient::builder()
+            .build("http://127.0.0.1:8545")
+            .unwrap();
+
+        let methods = [
+            method("net_version", AccessRule::Public),
+            method("eth_syncing", AccessRule::Authenticated),
+        ];
+        let module = passthrough_module(client.clone(), &methods, &policy(&methods)).unwrap();
+        let mut names = module.method_names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["eth_syncing", "net_version"]);
+
+        // Rules on the result need a known result type
+        let methods = [method("eth_x", AccessRule::Sender)];
+        let err = passthrough_module(client.clone(), &methods, &policy(&methods))
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("not supported"));
+
+        // The rule of the final policy is checked, not the one of the config
+        let methods = [method("eth_x", AccessRule::Public)];
+        let overridden = AccessPolicy::default()
+            .with_methods([("eth_x", AccessRule::SenderOrReceiver)])
+            .unwrap();
+        assert!(passthrough_module(client.clone(), &methods, &overridden).is_err());
+
+        // Duplicate methods
+        let methods = [
+            method("net_version", AccessRule::Public),
+            method("net_version", AccessRule::Full),
+        ];
+        assert!(passthrough_module(client, &methods, &policy(&methods[..1])).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_passthrough_authorization() {
+        // Nothing listens there, calls that pass the rule fail upstream
+        let client = HttpClient::builder().build("http://127.0.0.1:1").unwrap();
+        let methods = [
+            method("net_version", AccessRule::Public),
+            method("eth_syncing", AccessRule::Authenticated),
+        ];
+        let module = passthrough_module(client, &methods, &policy(&methods)).unwrap();
+
+        // Without the middleware stack, the handlers still check the request rules
+        let err = module
+            .call::<_, Value>("eth_syncing", rpc_params![])
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("unauthorized"));
+        let err = module
+            .call::<_, Value>("net_version", rpc_params![])
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("proxy_call_failed"));
+    }
+}
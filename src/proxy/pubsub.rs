@@ -0,0 +1,286 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use alloy_rpc_types::Log;
+use chrono::Utc;
+use hyper::http::Extensions;
+use jsonrpsee::core::client::{Subscription, SubscriptionClientT};
+use jsonrpsee::core::params::ArrayParams;
+use jsonrpsee::core::server::ConnectionId;
+use jsonrpsee::core::{SubscriptionResult, async_trait};
+use jsonrpsee::types::ErrorObjectOwned;
+use jsonrpsee::ws_client::{WsClient, WsClientBuilder};
+use jsonrpsee::{PendingSubscriptionSink, SubscriptionMessage};
+use serde_json::Value;
+use serde_json::value::to_raw_value;
+use tokio::sync::Mutex;
+
+use super::error::{invalid_params, proxy_call_failed};
+use super::interface::EthPubSubProxyServer;
+use super::visibility::{is_log_visible, redact_header};
+use crate::auth::{
+    AccessPolicy, AccessRule, Session, SharedRevocationList, WsSessions, get_session,
+};
+
+// How often the session of a subscription is checked for revocation and `ws_authenticate`
+const SESSION_CHECK_SECS: u64 = 5;
+
+/// Subscription kinds relayed to the validium node
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SubscriptionKind {
+    NewHeads,
+    Logs,
+}
+
+impl SubscriptionKind {
+    fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "newHeads" => Some(Self::NewHeads),
+            "logs" => Some(Self::Logs),
+            _ => None,
+        }
+    }
+
+    /// Filter or redact a notification for the subscriber, `None` if it is not visible
+    fn view(self, session: &Session, mut item: Value) -> Option<Value> {
+        match self {
+            Self::NewHeads => {
+                if !session.access.has_global_read_access() {
+                    redact_header(&mut item);
+                }
+                Some(item)
+            }
+            Self::Logs => {
+                let log: Log = serde_json::from_value(item.clone()).ok()?;
+                is_log_visible(&session.access, &log).then_some(item)
+            }
+        }
+    }
+}
+
+/// Relays `eth_subscribe` to a WebSocket connection to the validium node
+#[derive(Clone)]
+pub struct PubSubProxyImpl {
+    validium_ws_url: String,
+    // Connected on the first subscription, and again after the connection is lost
+    upstream: Arc<Mutex<Option<Arc<WsClient>>>>,
+    sessions: WsSessions,
+    revoked_tokens: SharedRevocationList,
+    policy: Arc<AccessPolicy>,
+}
+
+impl PubSubProxyImpl {
+    pub fn new(
+        validium_ws_url: &str,
+        sessions: WsSessions,
+        revoked_tokens: SharedRevocationList,
+        policy: Arc<AccessPolicy>,
+    ) -> Self {
+        Self {
+            validium_ws_url: validium_ws_url.to_string(),
+            upstream: Arc::new(Mutex::new(None)),
+            sessions,
+            revoked_tokens,
+            policy,
+        }
+    }
+
+    /// Check the `eth_subscribe` rule in the handler too, in case it is reached without
+    /// `AccessPolicyMiddleware`
+    fn check_subscribe(&self, session: &Session) -> Result<(), ErrorObjectOwned> {
+        self.policy
+            .rule("eth_subscribe")
+            .unwrap_or(AccessRule::Deny)
+            .check_request(&session.access, None)
+    }
+
+    /// The session of a subscription now: the session set with `ws_authenticate` replaces
+    /// the current one. `None` once its token is expired or revoked, or it may no longer
+    /// subscribe.
+    async fn current_session(&self, conn: ConnectionId, session: Session) -> Option<Session> {
+        let now = Utc::now().timestamp();
+        let session = self.sessions.get(&conn, now).unwrap_or(session);
+        if session.is_expired(now) || self.check_subscribe(&session).is_err() {
+            return None;
+        }
+        if let Some(jti) = &session.token_id
+            && self.revoked_tokens.is_revoked(jti).await
+        {
+            return None;
+        }
+        Some(session)
+    }
+
+    async fn upstream(&self) -> Result<Arc<WsClient>, ErrorObjectOwned> {
+        let mut upstream = self.upstream.lock().await;
+        if let Some(client) = upstream.as_ref()
+            && client.is_connected()
+        {
+            return Ok(client.clone());
+        }
+
+        let client = WsClientBuilder::default()
+            .build(&self.validium_ws_url)
+            .await
+            .map_err(proxy_call_failed)?;
+        let client = Arc::new(client);
+        *upstream = Some(client.clone());
+        Ok(client)
+    }
+
+    async fn subscribe_upstream(
+        &self,
+        kind: &str,
+        params: Option<Value>,
+    ) -> Result<Subscription<Value>, ErrorObjectOwned> {
+        let mut rpc_params = ArrayParams::new();
+        rpc_params
+            .insert(kind)
+            .map_err(|e| invalid_params(e.to_string()))?;
+        if let Some(params) = params {
+            rpc_params
+                .insert(params)
+                .map_err(|e| invalid_params(e.to_string()))?;
+        }
+
+        self.upstream()
+            .await?
+            .subscribe("eth_subscribe", rpc_params, "eth_unsubscribe")
+            .await
+            .map_err(proxy_call_failed)
+    }
+}
+
+/// Resolves when the token of the session expires, never for tokens without expiry
+async fn session_expired(session: &Session) {
+    match session.expires_at {
+        Some(exp) => {
+            let remaining = exp.saturating_sub(Utc::now().timestamp()).max(0) as u64;
+            tokio::time::sleep(Duration::from_secs(remaining)).await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+#[async_trait]
+impl EthPubSubProxyServer for PubSubProxyImpl {
+    async fn subscribe(
+        &self,
+        pending: PendingSubscriptionSink,
+        ext: &Extensions,
+        kind: String,
+        params: Option<Value>,
+    ) -> SubscriptionResult {
+        let Some(subscription_kind) = SubscriptionKind::parse(&kind) else {
+            pending
+                .reject(invalid_params(format!("unsupported subscription: {kind}")))
+                .await;
+            return Ok(());
+        };
+        if subscription_kind == SubscriptionKind::NewHeads && params.is_some() {
+            pending
+                .reject(invalid_params("newHeads takes no params"))
+                .await;
+            return Ok(());
+        }
+
+        let mut session = get_session(ext);
+        if let Err(e) = self.check_subscribe(&session) {
+            pending.reject(e).await;
+            return Ok(());
+        }
+
+        let mut upstream = match self.subscribe_upstream(&kind, params).await {
+            Ok(upstream) => upstream,
+            Err(e) => {
+                pending.reject(e).await;
+                return Ok(());
+            }
+        };
+        let sink = pending.accept().await?;
+        let mut check = tokio::time::interval(Duration::from_secs(SESSION_CHECK_SECS));
+
+        loop {
+            tokio::select! {
+                item = upstream.next() => {
+                    let Some(item) = item else {
+                        return Err("upstream subscription closed".into());
+                    };
+                    if let Some(item) = subscription_kind.view(&session, item?) {
+                        sink.send(SubscriptionMessage::from(to_raw_value(&item)?)).await?;
+                    }
+                }
+                // The connection may have been authenticated again with `ws_authenticate`
+                _ = session_expired(&session) => {
+                    session = self
+                        .current_session(sink.connection_id(), session)
+                        .await
+                        .ok_or("access token expired")?;
+                }
+                // The token may have been revoked with `siwe_signOut`, or the access
+                // lowered with `ws_authenticate`
+                _ = check.tick() => {
+                    session = self
+                        .current_session(sink.connection_id(), session)
+                        .await
+                        .ok_or("access revoked")?;
+                }
+                _ = sink.closed() => return Ok(()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{AccessLevel, InMemoryRevocationList};
+    use alloy::primitives::Address;
+
+    #[tokio::test]
+    async fn test_subscription_session() {
+        let revoked_tokens: SharedRevocationList = Arc::new(InMemoryRevocationList::new());
+        let sessions = WsSessions::new();
+        let pubsub = PubSubProxyImpl::new(
+            "ws://127.0.0.1:1",
+            sessions.clone(),
+            revoked_tokens.clone(),
+            Arc::new(AccessPolicy::default()),
+        );
+        let now = Utc::now().timestamp();
+        let conn = ConnectionId(1);
+        let session = Session {
+            access: AccessLevel::Basic(Address::repeat_byte(0x11)),
+            expires_at: Some(now + 60),
+            token_id: Some("token".to_string()),
+        };
+
+        assert!(pubsub.check_subscribe(&Session::anonymous()).is_err());
+        assert!(pubsub.check_subscribe(&session).is_ok());
+        assert_eq!(
+            pubsub.current_session(conn, session.clone()).await,
+            Some(session.clone())
+        );
+
+        // The session set with `ws_authenticate` replaces the one of the subscription
+        let full = Session {
+            access: AccessLevel::Full,
+            expires_at: None,
+            token_id: None,
+        };
+        sessions.insert(conn, full.clone());
+        assert_eq!(
+            pubsub.current_session(conn, session.clone()).await,
+            Some(full)
+        );
+        sessions.insert(conn, Session::anonymous());
+        assert_eq!(pubsub.current_session(conn, session.clone()).await, None);
+        sessions.remove(&conn);
+
+        // Signed out
+        revoked_tokens
+            .revoke("token".to_string(), (now + 60) as usize)
+            .await;
+        assert_eq!(pubsub.current_session(conn, session).await, None);
+    }
+}
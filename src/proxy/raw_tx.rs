@@ -0,0 +1,216 @@
+use std::fmt;
+
+use alloy::consensus::TxType;
+use alloy::consensus::transaction::{PooledTransaction, SignerRecoverable};
+use alloy::eips::eip2718::Decodable2718;
+use alloy::primitives::Address;
+use reth_primitives::TransactionSigned;
+
+/// The largest EIP-2718 transaction type byte, larger first bytes are legacy RLP lists
+const TX_TYPE_BYTE_MAX: u8 = 0x7f;
+
+/// Reasons for rejecting a raw transaction submitted via `eth_sendRawTransaction`
+#[derive(Debug, PartialEq)]
+pub enum RawTransactionError {
+    /// The payload is empty
+    Empty,
+    /// The payload is not a valid EIP-2718 encoded transaction
+    Decode(String),
+    /// The transaction type cannot be submitted to the pool (e.g. L1 messages)
+    NonPoolableType(u8),
+    /// The payload contains extra bytes after the transaction
+    TrailingBytes(usize),
+    /// The signer cannot be recovered from the signature
+    InvalidSignature,
+}
+
+impl fmt::Display for RawTransactionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "empty transaction"),
+            Self::Decode(e) => write!(f, "failed to decode transaction: {e}"),
+            Self::NonPoolableType(ty) => write!(f, "unsupported transaction type: {ty:#04x}"),
+            Self::TrailingBytes(n) => write!(f, "unexpected {n} trailing bytes after transaction"),
+            Self::InvalidSignature => write!(f, "invalid transaction signature"),
+        }
+    }
+}
+
+/// Decode an EIP-2718 encoded raw transaction and recover its signer
+pub fn decode_raw_transaction(
+    bytes: &[u8],
+) -> Result<(PooledTransaction, Address), RawTransactionError> {
+    let ty = match bytes.first() {
+        None => return Err(RawTransactionError::Empty),
+        Some(ty) if *ty <= TX_TYPE_BYTE_MAX => Some(*ty),
+        Some(_) => None, // legacy
+    };
+
+    // reject unknown types (e.g. L1 messages) before decoding the payload
+    if let Some(ty) = ty
+        && TxType::try_from(ty).is_err()
+    {
+        return Err(RawTransactionError::NonPoolableType(ty));
+    }
+
+    let mut slice = bytes;
+    let tx = TransactionSigned::decode_2718(&mut slice)
+        .map_err(|e| RawTransactionError::Decode(e.to_string()))?;
+
+    if !slice.is_empty() {
+        return Err(RawTransactionError::TrailingBytes(slice.len()));
+    }
+
+    // blob transactions are not poolable without their sidecar
+    let tx: PooledTransaction = tx
+        .try_into()
+        .map_err(|_| RawTransactionError::NonPoolableType(ty.unwrap_or_default()))?;
+
+    let from = tx
+        .recover_signer()
+        .map_err(|_| RawTransactionError::InvalidSignature)?;
+
+    Ok((tx, from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::consensus::{SignableTransaction, Transaction, TxEip1559, TxEnvelope, TxLegacy};
+    use alloy::eips::eip2718::Encodable2718;
+    use alloy::primitives::{Bytes, Signature, TxKind, U256, address};
+    use alloy::signers::SignerSync;
+    use alloy::signers::local::PrivateKeySigner;
+    use proptest::prelude::*;
+
+    fn signed_eip1559(signer: &PrivateKeySigner, nonce: u64, input: Vec<u8>) -> Vec<u8> {
+        let tx = TxEip1559 {
+            chain_id: 534352,
+            nonce,
+            gas_limit: 100_000,
+            to: TxKind::Call(address!("0x1234567890abcdef1234567890abcdef12345678")),
+            value: U256::from(1),
+            input: Bytes::from(input),
+            ..Default::default()
+        };
+        let signature = signer.sign_hash_sync(&tx.signature_hash()).unwrap();
+        TxEnvelope::from(tx.into_signed(signature)).encoded_2718()
+    }
+
+    fn signed_legacy(signer: &PrivateKeySigner, nonce: u64, input: Vec<u8>) -> Vec<u8> {
+        let tx = TxLegacy {
+            chain_id: Some(534352),
+            nonce,
+            gas_limit: 100_000,
+            to: TxKind::Call(address!("0x1234567890abcdef1234567890abcdef12345678")),
+            value: U256::from(1),
+            input: Bytes::from(input),
+            ..Default::default()
+        };
+        let signature = signer.sign_hash_sync(&tx.signature_hash()).unwrap();
+        TxEnvelope::from(tx.into_signed(signature)).encoded_2718()
+    }
+
+    #[test]
+    fn test_decode_raw_transaction_rejects_empty() {
+        assert_eq!(
+            decode_raw_transaction(&[]).unwrap_err(),
+            RawTransactionError::Empty
+        );
+    }
+
+    #[test]
+    fn test_decode_raw_transaction_rejects_l1_message() {
+        // L1 message transactions (type 0x7e) are never accepted from users
+        let mut bytes = vec![0x7e];
+        bytes.extend_from_slice(&signed_legacy(&PrivateKeySigner::random(), 0, vec![]));
+        assert_eq!(
+            decode_raw_transaction(&bytes).unwrap_err(),
+            RawTransactionError::NonPoolableType(0x7e)
+        );
+    }
+
+    #[test]
+    fn test_decode_raw_transaction_rejects_bad_signature() {
+        // a well-formed transaction with r = s = 0, no signer can be recovered
+        let tx = TxLegacy {
+            chain_id: Some(534352),
+            gas_limit: 100_000,
+            to: TxKind::Call(address!("0x1234567890abcdef1234567890abcdef12345678")),
+            value: U256::from(1),
+            ..Default::default()
+        };
+        let signature = Signature::new(U256::ZERO, U256::ZERO, false);
+        let bytes = TxEnvelope::from(tx.into_signed(signature)).encoded_2718();
+
+        assert_eq!(
+            decode_raw_transaction(&bytes).unwrap_err(),
+            RawTransactionError::InvalidSignature
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn proptest_decode_raw_transaction_never_panics(bytes in prop::collection::vec(any::<u8>(), 0..512)) {
+            let _ = decode_raw_transaction(&bytes);
+        }
+
+        #[test]
+        fn proptest_decode_raw_transaction_roundtrip(
+            key in prop::array::uniform32(1u8..),
+            nonce in any::<u64>(),
+            input in prop::collection::vec(any::<u8>(), 0..128),
+            legacy in any::<bool>(),
+        ) {
+            let signer = PrivateKeySigner::from_slice(&key).unwrap();
+            let bytes = if legacy {
+                signed_legacy(&signer, nonce, input.clone())
+            } else {
+                signed_eip1559(&signer, nonce, input.clone())
+            };
+
+            let (tx, from) = decode_raw_transaction(&bytes).unwrap();
+            prop_assert_eq!(from, signer.address());
+            prop_assert_eq!(tx.nonce(), nonce);
+            prop_assert_eq!(tx.input().as_ref(), input.as_slice());
+        }
+
+        #[test]
+        fn proptest_decode_raw_transaction_rejects_trailing_bytes(
+            trailing in prop::collection::vec(any::<u8>(), 1..64),
+            legacy in any::<bool>(),
+        ) {
+            let signer = PrivateKeySigner::random();
+            let mut bytes = if legacy {
+                signed_legacy(&signer, 0, vec![])
+            } else {
+                signed_eip1559(&signer, 0, vec![])
+            };
+            bytes.extend_from_slice(&trailing);
+
+            prop_assert_eq!(
+                decode_raw_transaction(&bytes).unwrap_err(),
+                RawTransactionError::TrailingBytes(trailing.len())
+            );
+        }
+
+        #[test]
+        fn proptest_decode_raw_transaction_rejects_truncated(
+            cut in 1usize..64,
+            legacy in any::<bool>(),
+        ) {
+            let signer = PrivateKeySigner::random();
+            let bytes = if legacy {
+                signed_legacy(&signer, 0, vec![0xaa; 64])
+            } else {
+                signed_eip1559(&signer, 0, vec![0xaa; 64])
+            };
+
+            let truncated = &bytes[..bytes.len() - cut];
+            prop_assert!(matches!(
+                decode_raw_transaction(truncated).unwrap_err(),
+                RawTransactionError::Decode(_)
+            ));
+        }
+    }
+}
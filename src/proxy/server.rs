@@ -1,66 +1,203 @@
-use alloy::consensus::transaction::{PooledTransaction, SignerRecoverable};
-use alloy::primitives::{Address, B256, Bytes, U64, U256};
+use alloy::primitives::{Address, B256, Bytes, Selector, TxKind, U64, U256};
 use alloy::rpc::types::BlockId;
 use alloy::serde::JsonStorageKey;
 use alloy_network_primitives::ReceiptResponse;
-use alloy_rlp::Decodable;
 use alloy_rpc_types::{
     BlockNumberOrTag, FeeHistory, Filter, Log, TransactionRequest, TransactionTrait,
 };
 use hyper::http::Extensions;
-use jsonrpsee::core::{RpcResult, async_trait};
+use jsonrpsee::core::{ClientError, RpcResult, async_trait};
 use jsonrpsee::http_client::HttpClient;
-use reth_primitives::TransactionSigned;
 use scroll_alloy_rpc_types::{ScrollTransactionReceipt as Receipt, Transaction};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::Arc;
 
-use super::error::{internal_error, proxy_call_failed, unauthorized};
+use super::call_allowlist::CallAllowlist;
+use super::error::{
+    call_not_allowed, internal_error, invalid_params, proxy_call_failed, unauthorized,
+};
 use super::interface::{
     Block, EthRpcProxyClient, EthRpcProxyServer, ScrollRpcProxyClient, ScrollRpcProxyServer,
-    Withdrawal,
+    Withdrawal, WithdrawalStatus, WithdrawalsPage,
 };
-use crate::auth::AccessLevel;
+use super::logs::{check_block_range, check_logs_filter, needs_head};
+use super::raw_tx::decode_raw_transaction;
+use super::visibility::{is_log_visible, is_transfer_party, redact_block, redact_receipt_logs};
+use super::whitelist::TxWhitelist;
+use crate::auth::{AccessLevel, AccessPolicy, AccessRule, get_access};
+use crate::metrics::metrics;
+
+/// Call `$method` of the `$api` client on an upstream, the duration is recorded per upstream
+macro_rules! upstream_call {
+    ($upstream:literal, $api:ident, $client:expr, $method:ident $(, $arg:expr )* ) => {
+        metrics()
+            .time_upstream($upstream, stringify!($method), $api::$method(&$client $(, $arg )*))
+            .await
+            .map_err(|e| proxy_call_failed(e))
+    };
+}
 
 macro_rules! proxy_call {
     ($client:expr, $method:ident $(, $arg:expr )* ) => {
-        EthRpcProxyClient::$method(&$client $(, $arg )*).await.map_err(|e| proxy_call_failed(e))
+        upstream_call!("validium", EthRpcProxyClient, $client, $method $(, $arg )*)
     };
 }
 
-fn get_access(ext: &Extensions) -> &AccessLevel {
-    ext.get::<AccessLevel>().unwrap_or(&AccessLevel::None)
-}
-
-fn only_authorized(ext: &Extensions, address: &Address) -> RpcResult<()> {
-    if !get_access(ext).is_authorized(address) {
-        return Err(unauthorized());
-    }
-    Ok(())
-}
-
-fn only_full_access(ext: &Extensions) -> RpcResult<()> {
-    if get_access(ext) != &AccessLevel::Full {
-        return Err(unauthorized());
-    }
-    Ok(())
-}
-
+#[derive(Clone)]
 pub struct RpcProxyImpl {
     validium_client: HttpClient,
     withdraw_proofs_client: HttpClient,
+    tx_whitelist: TxWhitelist,
+    call_allowlist: CallAllowlist,
+    // request rules are checked by `AccessPolicyMiddleware` and again here, result rules here
+    policy: Arc<AccessPolicy>,
+    // Blocks searched by `eth_getLogs` of users without global read access
+    max_logs_block_range: u64,
+    // Only return the receipt logs involving users without global read access
+    redact_receipt_logs: bool,
+    // Events whose parties may view the transaction that emitted them
+    transfer_events: Arc<HashSet<B256>>,
 }
 
 impl RpcProxyImpl {
+    /// `transfer_events` let their parties view the transactions that emitted them, with the
+    /// `sender_or_receiver` rule, e.g. the recipients of ERC-20 transfers
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         validium_url: impl AsRef<str>,
         withdraw_proofs_url: impl AsRef<str>,
+        tx_whitelist: TxWhitelist,
+        call_allowlist: CallAllowlist,
+        policy: Arc<AccessPolicy>,
+        max_logs_block_range: u64,
+        redact_receipt_logs: bool,
+        transfer_events: HashSet<B256>,
     ) -> anyhow::Result<Self> {
         let validium_client = HttpClient::builder().build(validium_url)?;
         let withdraw_proofs_client = HttpClient::builder().build(withdraw_proofs_url)?;
         Ok(Self {
             validium_client,
             withdraw_proofs_client,
+            tx_whitelist,
+            call_allowlist,
+            policy,
+            max_logs_block_range,
+            redact_receipt_logs,
+            transfer_events: Arc::new(transfer_events),
         })
     }
+
+    /// Whether the parties of transfer events may view a transaction under `rule`
+    fn allows_transfer_parties(&self, rule: AccessRule) -> bool {
+        rule == AccessRule::SenderOrReceiver && !self.transfer_events.is_empty()
+    }
+
+    /// Whether the user is a party of a transfer event of the receipt
+    fn is_transfer_party(&self, access: &AccessLevel, receipt: &Receipt) -> bool {
+        receipt
+            .inner
+            .inner
+            .logs()
+            .iter()
+            .any(|log| is_transfer_party(access, &self.transfer_events, log))
+    }
+
+    /// The access rule of `method`, unknown methods are denied
+    fn rule(&self, method: &str) -> AccessRule {
+        self.policy.rule(method).unwrap_or(AccessRule::Deny)
+    }
+
+    /// Check the request rule of `method` in the handler too, in case it is reached without
+    /// `AccessPolicyMiddleware`. `params` are the positional params of the call, they are only
+    /// serialized for rules on an address param.
+    fn check_request(
+        &self,
+        ext: &Extensions,
+        method: &str,
+        params: impl Serialize,
+    ) -> RpcResult<AccessRule> {
+        let rule = self.rule(method);
+        let params = match rule {
+            AccessRule::SelfAddress { .. } => Some(
+                serde_json::value::to_raw_value(&params)
+                    .map_err(|e| internal_error(e.to_string()))?,
+            ),
+            _ => None,
+        };
+        rule.check_request(get_access(ext), params.as_deref())?;
+        Ok(rule)
+    }
+
+    /// Check `eth_call` and `eth_estimateGas` of users without global read access: the call must
+    /// be sent from their address to an allowlisted read-only function. With basic access, gas can
+    /// also be estimated for the transactions `eth_sendRawTransaction` accepts from them, the tx
+    /// whitelist does not apply to API keys.
+    fn check_call(
+        &self,
+        access: &AccessLevel,
+        request: &TransactionRequest,
+        estimate: bool,
+    ) -> RpcResult<()> {
+        if !request.from.is_some_and(|from| access.is_authorized(&from)) {
+            return Err(call_not_allowed("from must be your address"));
+        }
+        if request.authorization_list.is_some() {
+            return Err(call_not_allowed("authorization list not allowed"));
+        }
+        let Some(TxKind::Call(to)) = request.to else {
+            return Err(call_not_allowed("contract deployment not allowed"));
+        };
+        let input = request
+            .input
+            .unique_input()
+            .map_err(|e| invalid_params(e.to_string()))?
+            .map(|input| input.as_ref())
+            .unwrap_or_default();
+
+        if estimate && matches!(access, AccessLevel::Basic(_)) {
+            match input.get(..4) {
+                None if input.is_empty() => return Ok(()),
+                Some(selector)
+                    if self
+                        .tx_whitelist
+                        .check(&to, &Selector::from_slice(selector))
+                        .is_ok() =>
+                {
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+
+        self.call_allowlist.check(access, &to, input)
+    }
+
+    /// The client connected to the validium upstream
+    pub fn validium_client(&self) -> HttpClient {
+        self.validium_client.clone()
+    }
+
+    /// Query the chain id of the validium upstream
+    pub async fn validium_chain_id(&self) -> anyhow::Result<u64> {
+        EthRpcProxyClient::chain_id(&self.validium_client)
+            .await?
+            .map(|id| id.to::<u64>())
+            .ok_or_else(|| anyhow::anyhow!("validium chain id not available"))
+    }
+
+    /// Check that the withdraw-proofs upstream answers, errors returned by the service count as answers
+    pub async fn check_withdraw_proofs(&self) -> anyhow::Result<()> {
+        match ScrollRpcProxyClient::withdrawal_by_message_hash(
+            &self.withdraw_proofs_client,
+            B256::ZERO,
+        )
+        .await
+        {
+            Ok(_) | Err(ClientError::Call(_)) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
 }
 
 #[async_trait]
@@ -71,10 +208,15 @@ impl ScrollRpcProxyServer for RpcProxyImpl {
         block_id: String,
         mode: String,
     ) -> RpcResult<Option<Vec<Transaction>>> {
-        only_full_access(ext)?;
-        ScrollRpcProxyClient::l1_messages_in_block(&self.validium_client, block_id, mode)
-            .await
-            .map_err(proxy_call_failed)
+        self.check_request(ext, "scroll_getL1MessagesInBlock", (&block_id, &mode))?;
+        upstream_call!(
+            "validium",
+            ScrollRpcProxyClient,
+            self.validium_client,
+            l1_messages_in_block,
+            block_id,
+            mode
+        )
     }
 
     async fn withdrawals_by_transaction(
@@ -83,19 +225,18 @@ impl ScrollRpcProxyServer for RpcProxyImpl {
         tx_hash: B256,
     ) -> RpcResult<Vec<Withdrawal>> {
         let access = get_access(ext);
-
-        // pre-check before proxy call
-        if access == &AccessLevel::None {
-            return Err(unauthorized());
-        }
+        let rule = self.check_request(ext, "scroll_withdrawalsByTransaction", (&tx_hash,))?;
 
         // proxy call
-        let ws =
-            ScrollRpcProxyClient::withdrawals_by_transaction(&self.withdraw_proofs_client, tx_hash)
-                .await
-                .map_err(proxy_call_failed)?;
-
-        if ws.is_empty() || access == &AccessLevel::Full {
+        let ws = upstream_call!(
+            "withdraw_proofs",
+            ScrollRpcProxyClient,
+            self.withdraw_proofs_client,
+            withdrawals_by_transaction,
+            tx_hash
+        )?;
+
+        if ws.is_empty() || access == &AccessLevel::Full || !rule.checks_result() {
             return Ok(ws);
         }
 
@@ -108,7 +249,7 @@ impl ScrollRpcProxyServer for RpcProxyImpl {
             Some(tx) => tx,
         };
 
-        if access.is_authorized(&tx.as_recovered().signer()) {
+        if rule.check_result(access, &tx.as_recovered().signer(), tx.to().as_ref()) {
             return Ok(ws);
         }
 
@@ -121,21 +262,18 @@ impl ScrollRpcProxyServer for RpcProxyImpl {
         message_hash: B256,
     ) -> RpcResult<Option<Withdrawal>> {
         let access = get_access(ext);
-
-        // pre-check before proxy call
-        if access == &AccessLevel::None {
-            return Err(unauthorized());
-        }
+        let rule = self.check_request(ext, "scroll_withdrawalByMessageHash", (&message_hash,))?;
 
         // proxy call
-        let maybe_w = ScrollRpcProxyClient::withdrawal_by_message_hash(
-            &self.withdraw_proofs_client,
-            message_hash,
-        )
-        .await
-        .map_err(proxy_call_failed)?;
-
-        if maybe_w.is_none() || access == &AccessLevel::Full {
+        let maybe_w = upstream_call!(
+            "withdraw_proofs",
+            ScrollRpcProxyClient,
+            self.withdraw_proofs_client,
+            withdrawal_by_message_hash,
+            message_hash
+        )?;
+
+        if maybe_w.is_none() || access == &AccessLevel::Full || !rule.checks_result() {
             return Ok(maybe_w);
         }
 
@@ -150,12 +288,41 @@ impl ScrollRpcProxyServer for RpcProxyImpl {
             Some(tx) => tx,
         };
 
-        if access.is_authorized(&tx.as_recovered().signer()) {
+        if rule.check_result(access, &tx.as_recovered().signer(), tx.to().as_ref()) {
             return Ok(Some(w));
         }
 
         Err(unauthorized())
     }
+
+    async fn withdrawals_by_address(
+        &self,
+        ext: &Extensions,
+        address: Address,
+        cursor: Option<String>,
+        status: Option<WithdrawalStatus>,
+    ) -> RpcResult<WithdrawalsPage> {
+        self.check_request(
+            ext,
+            "scroll_withdrawalsByAddress",
+            (&address, &cursor, &status),
+        )?;
+        let mut page = upstream_call!(
+            "withdraw_proofs",
+            ScrollRpcProxyClient,
+            self.withdraw_proofs_client,
+            withdrawals_by_address,
+            address,
+            cursor,
+            status
+        )?;
+
+        // don't rely on the withdraw-proofs service to only return withdrawals of `address`
+        // with the requested status
+        page.withdrawals
+            .retain(|w| w.from == address && status.is_none_or(|status| w.status() == status));
+        Ok(page)
+    }
 }
 
 #[async_trait]
@@ -174,8 +341,15 @@ impl EthRpcProxyServer for RpcProxyImpl {
         hash: B256,
         full: bool,
     ) -> RpcResult<Option<Block>> {
-        only_full_access(ext)?;
-        proxy_call!(self.validium_client, block_by_hash, hash, full)
+        self.check_request(ext, "eth_getBlockByHash", (&hash, full))?;
+        let access = get_access(ext);
+        if access.has_global_read_access() {
+            return proxy_call!(self.validium_client, block_by_hash, hash, full);
+        }
+
+        // full transactions are needed to find those of the user
+        let block = proxy_call!(self.validium_client, block_by_hash, hash, true)?;
+        Ok(block.map(|block| redact_block(access, block, full)))
     }
 
     async fn block_by_number(
@@ -184,8 +358,15 @@ impl EthRpcProxyServer for RpcProxyImpl {
         number: BlockNumberOrTag,
         full: bool,
     ) -> RpcResult<Option<Block>> {
-        only_full_access(ext)?;
-        proxy_call!(self.validium_client, block_by_number, number, full)
+        self.check_request(ext, "eth_getBlockByNumber", (&number, full))?;
+        let access = get_access(ext);
+        if access.has_global_read_access() {
+            return proxy_call!(self.validium_client, block_by_number, number, full);
+        }
+
+        // full transactions are needed to find those of the user
+        let block = proxy_call!(self.validium_client, block_by_number, number, true)?;
+        Ok(block.map(|block| redact_block(access, block, full)))
     }
 
     async fn balance(
@@ -194,7 +375,7 @@ impl EthRpcProxyServer for RpcProxyImpl {
         address: Address,
         block_number: Option<BlockId>,
     ) -> RpcResult<U256> {
-        only_authorized(ext, &address)?;
+        self.check_request(ext, "eth_getBalance", (&address, &block_number))?;
         proxy_call!(self.validium_client, balance, address, block_number)
     }
 
@@ -205,7 +386,7 @@ impl EthRpcProxyServer for RpcProxyImpl {
         index: JsonStorageKey,
         block_number: Option<BlockId>,
     ) -> RpcResult<B256> {
-        only_full_access(ext)?;
+        self.check_request(ext, "eth_getStorageAt", (&address, &index, &block_number))?;
         proxy_call!(
             self.validium_client,
             storage_at,
@@ -221,11 +402,7 @@ impl EthRpcProxyServer for RpcProxyImpl {
         hash: B256,
     ) -> RpcResult<Option<Transaction>> {
         let access = get_access(ext);
-
-        // pre-check before proxy call
-        if access == &AccessLevel::None {
-            return Err(unauthorized());
-        }
+        let rule = self.check_request(ext, "eth_getTransactionByHash", (&hash,))?;
 
         // proxy call
         let maybe_tx = proxy_call!(self.validium_client, transaction_by_hash, hash)?;
@@ -235,14 +412,17 @@ impl EthRpcProxyServer for RpcProxyImpl {
             Some(tx) => tx,
         };
 
-        // allow receiver to query transaction
-        if access.is_authorized(&tx.to().unwrap_or_default()) {
+        // allow sender or receiver to query transaction
+        if rule.check_result(access, &tx.as_recovered().signer(), tx.to().as_ref()) {
             return Ok(Some(tx));
         }
 
-        // allow sender to query transaction
-        if access.is_authorized(&tx.as_recovered().signer()) {
-            return Ok(Some(tx));
+        // or a party of a transfer, e.g. the recipient of a token
+        if self.allows_transfer_parties(rule) && access != &AccessLevel::None {
+            let receipt = proxy_call!(self.validium_client, transaction_receipt, hash)?;
+            if receipt.is_some_and(|receipt| self.is_transfer_party(access, &receipt)) {
+                return Ok(Some(tx));
+            }
         }
 
         Err(unauthorized())
@@ -254,31 +434,29 @@ impl EthRpcProxyServer for RpcProxyImpl {
         hash: B256,
     ) -> RpcResult<Option<Receipt>> {
         let access = get_access(ext);
-
-        // pre-check before proxy call
-        if access == &AccessLevel::None {
-            return Err(unauthorized());
-        }
+        let rule = self.check_request(ext, "eth_getTransactionReceipt", (&hash,))?;
 
         // proxy call
         let maybe_receipt = proxy_call!(self.validium_client, transaction_receipt, hash)?;
 
-        let receipt = match maybe_receipt {
+        let mut receipt = match maybe_receipt {
             None => return Ok(None),
             Some(receipt) => receipt,
         };
 
-        // allow receiver to query transaction
-        if access.is_authorized(&receipt.to().unwrap_or_default()) {
-            return Ok(Some(receipt));
+        // allow sender or receiver, or a party of a transfer, to query transaction
+        if !rule.check_result(access, &receipt.from(), receipt.to().as_ref())
+            && !(self.allows_transfer_parties(rule) && self.is_transfer_party(access, &receipt))
+        {
+            return Err(unauthorized());
         }
-
-        // allow sender to query transaction
-        if access.is_authorized(&receipt.from()) {
+        if !self.redact_receipt_logs || access.has_global_read_access() {
             return Ok(Some(receipt));
         }
 
-        Err(unauthorized())
+        // the transaction may have emitted logs of other users
+        redact_receipt_logs(access, &mut receipt);
+        Ok(Some(receipt))
     }
 
     async fn transaction_count(
@@ -287,7 +465,7 @@ impl EthRpcProxyServer for RpcProxyImpl {
         address: Address,
         block_number: Option<BlockId>,
     ) -> RpcResult<U256> {
-        only_authorized(ext, &address)?;
+        self.check_request(ext, "eth_getTransactionCount", (&address, &block_number))?;
         proxy_call!(
             self.validium_client,
             transaction_count,
@@ -302,7 +480,7 @@ impl EthRpcProxyServer for RpcProxyImpl {
         address: Address,
         block_number: Option<BlockId>,
     ) -> RpcResult<Bytes> {
-        only_full_access(ext)?;
+        self.check_request(ext, "eth_getCode", (&address, &block_number))?;
         proxy_call!(self.validium_client, get_code, address, block_number)
     }
 
@@ -312,7 +490,11 @@ impl EthRpcProxyServer for RpcProxyImpl {
         request: TransactionRequest,
         block_number: Option<BlockId>,
     ) -> RpcResult<Bytes> {
-        only_full_access(ext)?;
+        self.check_request(ext, "eth_call", (&request, &block_number))?;
+        let access = get_access(ext);
+        if !access.has_global_read_access() {
+            self.check_call(access, &request, false)?;
+        }
         proxy_call!(self.validium_client, call, request, block_number)
     }
 
@@ -322,7 +504,11 @@ impl EthRpcProxyServer for RpcProxyImpl {
         request: TransactionRequest,
         block_number: Option<BlockId>,
     ) -> RpcResult<U256> {
-        only_full_access(ext)?;
+        self.check_request(ext, "eth_estimateGas", (&request, &block_number))?;
+        let access = get_access(ext);
+        if !access.has_global_read_access() {
+            self.check_call(access, &request, true)?;
+        }
         proxy_call!(self.validium_client, estimate_gas, request, block_number)
     }
 
@@ -351,33 +537,44 @@ impl EthRpcProxyServer for RpcProxyImpl {
     }
 
     async fn send_raw_transaction(&self, ext: &Extensions, bytes: Bytes) -> RpcResult<B256> {
+        self.check_request(ext, "eth_sendRawTransaction", (&bytes,))?;
         let access = get_access(ext);
 
-        // pre-check before tx decoding
-        if access == &AccessLevel::None {
-            return Err(unauthorized());
-        }
-
         // for basic access, you can only send your own transactions,
         // and contract deployment is not allowed.
         if matches!(access, AccessLevel::Basic(_)) {
-            let mut slice: &[u8] = bytes.as_ref();
-            let tx: PooledTransaction = TransactionSigned::decode(&mut slice)
-                .unwrap()
-                .try_into()
-                .unwrap();
+            let (tx, from) =
+                decode_raw_transaction(&bytes).map_err(|e| invalid_params(e.to_string()))?;
 
-            let from = tx.recover_signer().unwrap();
-            let to = tx.to();
-            let selector = tx.function_selector();
+            let to = match tx.to() {
+                Some(to) => to,
+                None => return Err(unauthorized()),
+            };
 
-            if !access.is_authorized(&from) || to.is_none() {
+            if !access.is_authorized(&from) {
                 return Err(unauthorized());
             }
 
-            if selector.is_some() {
-                // check `to` for whitelist
-                // check `selector` for whitelist
+            // contract calls are only allowed for whitelisted (to, selector) pairs,
+            // calldata too short to contain a selector would hit the fallback function.
+            match tx.function_selector() {
+                Some(selector) => self.tx_whitelist.check(&to, selector)?,
+                None if !tx.input().is_empty() => {
+                    return Err(invalid_params("calldata too short for function selector"));
+                }
+                None => {}
+            }
+        }
+
+        // API keys limited to some addresses can only send transactions of these addresses
+        if let AccessLevel::Scoped(scope) = access
+            && scope.is_address_scoped()
+        {
+            let (_, from) =
+                decode_raw_transaction(&bytes).map_err(|e| invalid_params(e.to_string()))?;
+
+            if !access.is_authorized(&from) {
+                return Err(unauthorized());
             }
         }
 
@@ -385,7 +582,351 @@ impl EthRpcProxyServer for RpcProxyImpl {
     }
 
     async fn logs(&self, ext: &Extensions, filter: Filter) -> RpcResult<Vec<Log>> {
-        only_full_access(ext)?;
-        proxy_call!(self.validium_client, logs, filter)
+        self.check_request(ext, "eth_getLogs", (&filter,))?;
+        let access = get_access(ext);
+        if access.has_global_read_access() {
+            return proxy_call!(self.validium_client, logs, filter);
+        }
+
+        // other users only get logs involving their address, from a limited block range
+        check_logs_filter(access, &filter)?;
+        let head = if needs_head(&filter) {
+            proxy_call!(self.validium_client, block_number)?.to::<u64>()
+        } else {
+            0
+        };
+        check_block_range(&filter, head, self.max_logs_block_range)?;
+
+        let logs = proxy_call!(self.validium_client, logs, filter)?;
+        Ok(logs
+            .into_iter()
+            .filter(|log| is_log_visible(access, log))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::header::{HeaderMap, HeaderValue};
+    use jsonrpsee::RpcModule;
+    use jsonrpsee::core::client::ClientT;
+    use jsonrpsee::core::middleware::RpcServiceBuilder;
+    use jsonrpsee::core::params::BatchRequestBuilder;
+    use jsonrpsee::rpc_params;
+    use jsonrpsee::server::{Server, ServerHandle};
+    use serde_json::Value;
+    use tower::ServiceBuilder;
+    use tower_http::auth::AsyncRequireAuthorizationLayer;
+
+    use crate::auth::{
+        AccessPolicyMiddleware, ApiKeyConfig, ApiKeyMethods, ApiKeyScopeMiddleware, ApiKeys,
+        AuthenticationMiddleware, InMemoryRevocationList, JwtSigner, JwtSignerKeyConfig,
+    };
+    use crate::service::{BatchLimitMiddleware, MaxBatchSize, RpcLoggerMiddleware};
+    use crate::test_utils::mock_server;
+
+    const ADMIN_KEY: &str = "admin-key";
+    const BALANCE_KEY: &str = "balance-key";
+
+    fn user() -> Address {
+        Address::repeat_byte(0x11)
+    }
+
+    fn withdrawal(from: Address) -> Withdrawal {
+        Withdrawal {
+            tx_hash: B256::repeat_byte(0x01),
+            message_hash: B256::repeat_byte(0x02),
+            from,
+            to: from,
+            value: U256::from(1),
+            nonce: 0,
+            message: Bytes::new(),
+            batch_index: 1,
+            proof: Bytes::new(),
+        }
+    }
+
+    /// Upstream answering the methods used in the tests with fixed values
+    async fn mock_validium() -> (String, ServerHandle) {
+        let mut module = RpcModule::new(());
+        module
+            .register_method("eth_getBalance", |_, _, _| "0x1")
+            .unwrap();
+        module
+            .register_method("eth_getTransactionCount", |_, _, _| "0x2")
+            .unwrap();
+        module
+            .register_method("eth_getCode", |_, _, _| "0xfe")
+            .unwrap();
+
+        // also serves as withdraw-proofs service, returns a withdrawal of someone else too
+        let page = WithdrawalsPage {
+            withdrawals: vec![withdrawal(user()), withdrawal(Address::ZERO)],
+            next_cursor: Some("next".to_string()),
+        };
+        module
+            .register_method("scroll_withdrawalsByAddress", move |_, _, _| page.clone())
+            .unwrap();
+
+        mock_server(module).await
+    }
+
+    fn proxy_server(validium_url: &str, policy: Arc<AccessPolicy>) -> RpcProxyImpl {
+        RpcProxyImpl::new(
+            validium_url,
+            validium_url,
+            TxWhitelist::from_config(&[]),
+            CallAllowlist::default(),
+            policy,
+            10_000,
+            false,
+            HashSet::new(),
+        )
+        .unwrap()
+    }
+
+    /// The proxy with the middleware stack of the server
+    async fn proxy(max_batch_size: MaxBatchSize) -> (String, JwtSigner, Vec<ServerHandle>) {
+        let (validium_url, validium) = mock_validium().await;
+
+        let jwt = JwtSigner::from_config(
+            &[JwtSignerKeyConfig {
+                kid: "test".to_string(),
+                secret: "testsecret-testsecret-testsecret".to_string(),
+                ..Default::default()
+            }],
+            "test",
+        )
+        .unwrap();
+        let api_keys = ApiKeys::from_config(
+            &[ADMIN_KEY.to_string()],
+            &[ApiKeyConfig {
+                name: "partner".to_string(),
+                key: BALANCE_KEY.to_string(),
+                methods: Some(vec!["eth_getBalance".to_string()]),
+                addresses: None,
+                read_only: true,
+                expires_at: None,
+                enabled: true,
+            }],
+        )
+        .unwrap();
+        let policy = Arc::new(AccessPolicy::default());
+
+        let proxy_server = proxy_server(&validium_url, policy.clone());
+        let mut module = RpcModule::new(());
+        module
+            .merge(EthRpcProxyServer::into_rpc(proxy_server.clone()))
+            .unwrap();
+        module
+            .merge(ScrollRpcProxyServer::into_rpc(proxy_server))
+            .unwrap();
+
+        let http_middleware = ServiceBuilder::new().layer(AsyncRequireAuthorizationLayer::new(
+            AuthenticationMiddleware::new(
+                jwt.clone(),
+                api_keys,
+                Arc::new(InMemoryRevocationList::new()),
+                false,
+            ),
+        ));
+        let rpc_middleware = RpcServiceBuilder::new()
+            .layer_fn(RpcLoggerMiddleware::new)
+            .layer_fn(move |service| BatchLimitMiddleware::new(service, max_batch_size))
+            .layer_fn(|service| ApiKeyScopeMiddleware::new(service, ApiKeyMethods::new([])))
+            .layer_fn(move |service| AccessPolicyMiddleware::new(service, policy.clone()));
+
+        let server = Server::builder()
+            .set_http_middleware(http_middleware)
+            .set_rpc_middleware(rpc_middleware)
+            .build("127.0.0.1:0")
+            .await
+            .unwrap();
+        let url = format!("http://{}", server.local_addr().unwrap());
+        (url, jwt, vec![validium, server.start(module)])
+    }
+
+    fn client(url: &str, token: Option<&str>) -> HttpClient {
+        let mut headers = HeaderMap::new();
+        if let Some(token) = token {
+            let value = HeaderValue::from_str(&format!("Bearer {token}")).unwrap();
+            headers.insert("authorization", value);
+        }
+        HttpClient::builder()
+            .set_headers(headers)
+            .build(url)
+            .unwrap()
+    }
+
+    /// A batch calling every method once, with addresses of the user and of someone else
+    fn batch() -> BatchRequestBuilder<'static> {
+        let mut batch = BatchRequestBuilder::new();
+        batch
+            .insert("eth_getBalance", rpc_params![user(), "latest"])
+            .unwrap();
+        batch
+            .insert("eth_getBalance", rpc_params![Address::ZERO, "latest"])
+            .unwrap();
+        batch
+            .insert("eth_getTransactionCount", rpc_params![user(), "latest"])
+            .unwrap();
+        batch
+            .insert("eth_getCode", rpc_params![user(), "latest"])
+            .unwrap();
+        batch.insert("eth_gasPrice", rpc_params![]).unwrap();
+        batch
+    }
+
+    /// Send the batch and map each entry to its result, or `None` if it failed
+    async fn send_batch(client: &HttpClient, batch: BatchRequestBuilder<'_>) -> Vec<Option<Value>> {
+        client
+            .batch_request::<Value>(batch)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.ok())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_batch_authorization() {
+        let (url, jwt, _handles) = proxy(MaxBatchSize::default()).await;
+        let exp = (chrono::Utc::now().timestamp() + 3600) as usize;
+        let token = jwt.create_token(user(), exp).unwrap();
+
+        let balance = Some(Value::from("0x1"));
+        let count = Some(Value::from("0x2"));
+        let code = Some(Value::from("0xfe"));
+        let gas_price = Some(Value::from("0x0"));
+
+        // Basic access: only the user's own address, no global data
+        let results = send_batch(&client(&url, Some(&token)), batch()).await;
+        assert_eq!(
+            results,
+            vec![
+                balance.clone(),
+                None,
+                count.clone(),
+                None,
+                gas_price.clone()
+            ]
+        );
+
+        // No access: only public methods
+        let results = send_batch(&client(&url, None), batch()).await;
+        assert_eq!(results, vec![None, None, None, None, gas_price.clone()]);
+
+        // Invalid tokens are handled like no access
+        let results = send_batch(&client(&url, Some("invalid")), batch()).await;
+        assert_eq!(results, vec![None, None, None, None, gas_price.clone()]);
+
+        // Full access
+        let results = send_batch(&client(&url, Some(ADMIN_KEY)), batch()).await;
+        assert_eq!(
+            results,
+            vec![
+                balance.clone(),
+                balance.clone(),
+                count,
+                code,
+                gas_price.clone()
+            ]
+        );
+
+        // API key limited to eth_getBalance
+        let results = send_batch(&client(&url, Some(BALANCE_KEY)), batch()).await;
+        assert_eq!(results, vec![balance.clone(), balance, None, None, None]);
+    }
+
+    #[tokio::test]
+    async fn test_handler_authorization() {
+        // Without the middleware stack, the handlers still check the request rules
+        let (validium_url, _validium) = mock_validium().await;
+        let mut module = RpcModule::new(());
+        module
+            .merge(EthRpcProxyServer::into_rpc(proxy_server(
+                &validium_url,
+                Arc::new(AccessPolicy::default()),
+            )))
+            .unwrap();
+        let (url, _handle) = mock_server(module).await;
+        let client = client(&url, None);
+
+        let balance = EthRpcProxyClient::balance(&client, user(), None).await;
+        assert!(balance.unwrap_err().to_string().contains("unauthorized"));
+        assert!(
+            EthRpcProxyClient::get_code(&client, user(), None)
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            EthRpcProxyClient::gas_price(&client).await.unwrap(),
+            U256::ZERO
+        );
+    }
+
+    #[tokio::test]
+    async fn test_max_batch_size() {
+        let (url, jwt, _handles) = proxy(MaxBatchSize {
+            none: Some(2),
+            basic: Some(5),
+            ..Default::default()
+        })
+        .await;
+        let exp = (chrono::Utc::now().timestamp() + 3600) as usize;
+        let token = jwt.create_token(user(), exp).unwrap();
+
+        // Too large for requests without access
+        let result = client(&url, None).batch_request::<Value>(batch()).await;
+        assert!(result.is_err());
+
+        let mut small = BatchRequestBuilder::new();
+        small.insert("eth_gasPrice", rpc_params![]).unwrap();
+        small.insert("eth_blockNumber", rpc_params![]).unwrap();
+        let results = send_batch(&client(&url, None), small).await;
+        assert_eq!(results[0], Some(Value::from("0x0")));
+
+        // Within the limits of basic access, unlimited for full access
+        let results = send_batch(&client(&url, Some(&token)), batch()).await;
+        assert_eq!(results.len(), 5);
+        let results = send_batch(&client(&url, Some(ADMIN_KEY)), batch()).await;
+        assert_eq!(results.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_withdrawals_by_address() {
+        let (url, jwt, _handles) = proxy(MaxBatchSize::default()).await;
+        let exp = (chrono::Utc::now().timestamp() + 3600) as usize;
+        let token = jwt.create_token(user(), exp).unwrap();
+        let client = client(&url, Some(&token));
+
+        // Only withdrawals of the address are returned
+        let page: WithdrawalsPage = client
+            .request(
+                "scroll_withdrawalsByAddress",
+                rpc_params![user(), Option::<String>::None, WithdrawalStatus::Pending],
+            )
+            .await
+            .unwrap();
+        assert_eq!(page.withdrawals.len(), 1);
+        assert_eq!(page.withdrawals[0].from, user());
+        assert_eq!(page.next_cursor.as_deref(), Some("next"));
+
+        // Only withdrawals with the status, the withdrawal of the mock has no proof yet
+        let page: WithdrawalsPage = client
+            .request(
+                "scroll_withdrawalsByAddress",
+                rpc_params![user(), Option::<String>::None, WithdrawalStatus::Claimable],
+            )
+            .await
+            .unwrap();
+        assert!(page.withdrawals.is_empty());
+
+        // Withdrawals of other addresses
+        let result: Result<WithdrawalsPage, _> = client
+            .request("scroll_withdrawalsByAddress", rpc_params![Address::ZERO])
+            .await;
+        assert!(result.is_err());
     }
 }
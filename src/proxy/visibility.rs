@@ -0,0 +1,296 @@
+use std::collections::HashSet;
+
+use alloy::primitives::{Address, B256, Bloom, b256};
+use alloy_network_primitives::TransactionResponse;
+use alloy_rpc_types::{Block, BlockTransactions, Log};
+use scroll_alloy_consensus::ScrollReceiptEnvelope;
+use scroll_alloy_rpc_types::ScrollTransactionReceipt;
+use serde_json::{Value, json};
+
+use crate::auth::AccessLevel;
+
+/// Topic 0 of ERC-20 and ERC-721 `Transfer(address,address,uint256)` events
+pub const TRANSFER_EVENT: B256 =
+    b256!("ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef");
+
+/// Decode an address from a topic, indexed address parameters are left-padded with zeros
+pub fn topic_address(topic: &B256) -> Option<Address> {
+    topic[..12]
+        .iter()
+        .all(|b| *b == 0)
+        .then(|| Address::from_slice(&topic[12..]))
+}
+
+/// Whether the log was emitted by or involves (as an indexed address parameter) an address
+/// the user may access, the event signature in the first topic is skipped.
+pub fn is_log_visible(access: &AccessLevel, log: &Log) -> bool {
+    if access.has_global_read_access() || access.is_authorized(&log.address()) {
+        return true;
+    }
+
+    log.topics()
+        .iter()
+        .skip(1)
+        .filter_map(topic_address)
+        .any(|address| access.is_authorized(&address))
+}
+
+/// Whether the log is one of the recognized transfer `events` with an address the user may
+/// access as indexed parameter, e.g. the recipient of an ERC-20 `Transfer`.
+pub fn is_transfer_party(access: &AccessLevel, events: &HashSet<B256>, log: &Log) -> bool {
+    let Some((event, params)) = log.topics().split_first() else {
+        return false;
+    };
+
+    events.contains(event)
+        && params
+            .iter()
+            .filter_map(topic_address)
+            .any(|address| access.is_authorized(&address))
+}
+
+/// Zero the `logsBloom` of a JSON block header, it reveals the addresses and events
+/// of the logs of other users. The other fields are kept.
+pub fn redact_header(header: &mut Value) {
+    if let Some(bloom) = header.get_mut("logsBloom") {
+        *bloom = json!(Bloom::ZERO);
+    }
+}
+
+/// Remove the logs of a receipt the user may not see and zero its `logsBloom`
+pub fn redact_receipt_logs(access: &AccessLevel, receipt: &mut ScrollTransactionReceipt) {
+    let receipt = match &mut receipt.inner.inner {
+        ScrollReceiptEnvelope::Legacy(receipt)
+        | ScrollReceiptEnvelope::Eip2930(receipt)
+        | ScrollReceiptEnvelope::Eip1559(receipt)
+        | ScrollReceiptEnvelope::Eip7702(receipt)
+        | ScrollReceiptEnvelope::L1Message(receipt) => receipt,
+    };
+    receipt
+        .receipt
+        .logs
+        .retain(|log| is_log_visible(access, log));
+    receipt.logs_bloom = Bloom::ZERO;
+}
+
+/// Reduce a block to what the user may see: the transactions they sent or received
+/// (as hashes unless `full`), and a header redacted like the headers of `newHeads`.
+pub fn redact_block<T>(access: &AccessLevel, mut block: Block<T>, full: bool) -> Block<T>
+where
+    T: TransactionResponse,
+{
+    block.header.inner.logs_bloom = Bloom::ZERO;
+
+    let own = std::mem::replace(&mut block.transactions, BlockTransactions::Hashes(vec![]))
+        .into_transactions()
+        .filter(|tx| {
+            access.is_authorized(&tx.from()) || tx.to().is_some_and(|to| access.is_authorized(&to))
+        });
+    block.transactions = if full {
+        BlockTransactions::Full(own.collect())
+    } else {
+        BlockTransactions::Hashes(own.map(|tx| tx.tx_hash()).collect())
+    };
+    block
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::consensus::transaction::Recovered;
+    use alloy::consensus::{Signed, TxEnvelope, TxLegacy, TxReceipt};
+    use alloy::primitives::{Bytes, LogData, Signature, TxKind};
+    use alloy_rpc_types::Transaction;
+
+    fn log(address: Address, topics: Vec<B256>) -> Log {
+        Log {
+            inner: alloy::primitives::Log {
+                address,
+                data: LogData::new_unchecked(topics, Bytes::new()),
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_log_visibility() {
+        let user = Address::repeat_byte(0x11);
+        let token = Address::repeat_byte(0x22);
+        let access = AccessLevel::Basic(user);
+        let transfer = TRANSFER_EVENT;
+
+        assert!(is_log_visible(&access, &log(user, vec![])));
+        assert!(is_log_visible(
+            &access,
+            &log(token, vec![transfer, B256::ZERO, user.into_word()])
+        ));
+        assert!(!is_log_visible(
+            &access,
+            &log(token, vec![transfer, B256::ZERO, token.into_word()])
+        ));
+        // The event signature is not a parameter
+        assert!(!is_log_visible(
+            &access,
+            &log(token, vec![user.into_word()])
+        ));
+        // Not an address
+        assert!(!is_log_visible(
+            &access,
+            &log(token, vec![transfer, B256::repeat_byte(0x11)])
+        ));
+
+        assert!(is_log_visible(&AccessLevel::Full, &log(token, vec![])));
+        assert!(!is_log_visible(&AccessLevel::None, &log(user, vec![])));
+    }
+
+    fn tx(hash: u8, from: Address, to: Address) -> Transaction {
+        let tx = TxLegacy {
+            to: TxKind::Call(to),
+            ..Default::default()
+        };
+        let signed =
+            Signed::new_unchecked(tx, Signature::test_signature(), B256::repeat_byte(hash));
+        Transaction {
+            inner: Recovered::new_unchecked(TxEnvelope::Legacy(signed), from),
+            block_hash: None,
+            block_number: None,
+            transaction_index: None,
+            effective_gas_price: None,
+        }
+    }
+
+    #[test]
+    fn test_redact_block() {
+        let user = Address::repeat_byte(0x11);
+        let other = Address::repeat_byte(0x22);
+        let access = AccessLevel::Basic(user);
+
+        let mut block = Block::<Transaction>::default();
+        block.header.inner.number = 7;
+        block.header.inner.logs_bloom = Bloom::repeat_byte(0x04);
+        block.header.inner.state_root = B256::repeat_byte(0x05);
+        block.header.inner.transactions_root = B256::repeat_byte(0x06);
+        block.header.inner.receipts_root = B256::repeat_byte(0x07);
+        block.transactions = BlockTransactions::Full(vec![
+            tx(1, user, other),
+            tx(2, other, other),
+            tx(3, other, user),
+        ]);
+
+        let redacted = redact_block(&access, block.clone(), false);
+        assert_eq!(redacted.header.inner.number, 7);
+        assert_eq!(redacted.header.inner.logs_bloom, Bloom::ZERO);
+        assert_eq!(redacted.header.inner.state_root, B256::repeat_byte(0x05));
+        assert_eq!(redacted.header.inner.receipts_root, B256::repeat_byte(0x07));
+
+        // The same header redaction as for newHeads
+        let mut header = serde_json::to_value(&block.header).unwrap();
+        redact_header(&mut header);
+        assert_eq!(serde_json::to_value(&redacted.header).unwrap(), header);
+        assert_eq!(
+            redacted.transactions,
+            BlockTransactions::Hashes(vec![B256::repeat_byte(1), B256::repeat_byte(3)])
+        );
+
+        let redacted = redact_block(&access, block.clone(), true);
+        let hashes = redacted
+            .transactions
+            .txns()
+            .map(|tx| tx.tx_hash())
+            .collect::<Vec<_>>();
+        assert_eq!(hashes, vec![B256::repeat_byte(1), B256::repeat_byte(3)]);
+
+        let redacted = redact_block(&AccessLevel::Basic(Address::ZERO), block, true);
+        assert!(redacted.transactions.is_empty());
+    }
+
+    #[test]
+    fn test_redact_header() {
+        let mut header = json!({
+            "number": "0x1",
+            "hash": B256::repeat_byte(0x01),
+            "stateRoot": B256::repeat_byte(0x02),
+            "transactionsRoot": B256::repeat_byte(0x03),
+            "logsBloom": Bloom::repeat_byte(0x04),
+        });
+        redact_header(&mut header);
+
+        assert_eq!(header["number"], "0x1");
+        assert_eq!(header["hash"], json!(B256::repeat_byte(0x01)));
+        assert_eq!(header["stateRoot"], json!(B256::repeat_byte(0x02)));
+        assert_eq!(header["transactionsRoot"], json!(B256::repeat_byte(0x03)));
+        assert_eq!(header["logsBloom"], json!(Bloom::ZERO));
+
+        // Missing fields are not added
+        let mut header = json!({ "number": "0x1" });
+        redact_header(&mut header);
+        assert!(header.get("logsBloom").is_none());
+    }
+
+    #[test]
+    fn test_redact_receipt_logs() {
+        let user = Address::repeat_byte(0x11);
+        let token = Address::repeat_byte(0x22);
+        let transfer = TRANSFER_EVENT;
+        let own = log(token, vec![transfer, user.into_word(), token.into_word()]);
+        let other = log(token, vec![transfer, token.into_word(), token.into_word()]);
+
+        let receipt: ScrollTransactionReceipt = serde_json::from_value(json!({
+            "type": "0x2",
+            "status": "0x1",
+            "cumulativeGasUsed": "0x5208",
+            "logs": [own, other],
+            "logsBloom": Bloom::repeat_byte(0x04),
+            "transactionHash": B256::repeat_byte(0x01),
+            "transactionIndex": "0x0",
+            "blockHash": B256::repeat_byte(0x02),
+            "blockNumber": "0x1",
+            "gasUsed": "0x5208",
+            "effectiveGasPrice": "0x1",
+            "from": user,
+            "to": token,
+            "contractAddress": null,
+            "l1Fee": "0x0",
+        }))
+        .unwrap();
+
+        let mut redacted = receipt.clone();
+        redact_receipt_logs(&AccessLevel::Basic(user), &mut redacted);
+        assert_eq!(
+            redacted.inner.transaction_hash,
+            receipt.inner.transaction_hash
+        );
+        assert_eq!(redacted.inner.inner.logs(), [own]);
+        assert_eq!(redacted.inner.inner.bloom(), Bloom::ZERO);
+
+        let mut redacted = receipt.clone();
+        redact_receipt_logs(&AccessLevel::Full, &mut redacted);
+        assert_eq!(redacted.inner.inner.logs(), receipt.inner.inner.logs());
+    }
+
+    #[test]
+    fn test_transfer_party() {
+        let user = Address::repeat_byte(0x11);
+        let token = Address::repeat_byte(0x22);
+        let access = AccessLevel::Basic(user);
+        let transfer = TRANSFER_EVENT;
+        let events = HashSet::from([transfer]);
+
+        // Sender or recipient of a transfer
+        let received = log(token, vec![transfer, token.into_word(), user.into_word()]);
+        assert!(is_transfer_party(&access, &events, &received));
+        let sent = log(token, vec![transfer, user.into_word(), token.into_word()]);
+        assert!(is_transfer_party(&access, &events, &sent));
+
+        // Other transfers and events
+        let other = log(token, vec![transfer, token.into_word(), token.into_word()]);
+        assert!(!is_transfer_party(&access, &events, &other));
+        let approval = log(
+            token,
+            vec![B256::repeat_byte(0x01), token.into_word(), user.into_word()],
+        );
+        assert!(!is_transfer_party(&access, &events, &approval));
+        assert!(!is_transfer_party(&access, &events, &log(user, vec![])));
+        assert!(!is_transfer_party(&access, &HashSet::new(), &received));
+    }
+}
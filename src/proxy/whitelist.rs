@@ -0,0 +1,84 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use alloy::primitives::{Address, Selector};
+use jsonrpsee::core::RpcResult;
+use serde::Deserialize;
+
+use super::error::not_whitelisted;
+
+/// Configuration for a single whitelisted contract
+#[derive(Debug, Deserialize)]
+pub struct TxWhitelistEntryConfig {
+    pub to: Address,
+    pub selectors: Vec<Selector>,
+}
+
+/// The set of (contract, selector) pairs that basic users are allowed to call
+#[derive(Clone)]
+pub struct TxWhitelist {
+    entries: Arc<HashMap<Address, HashSet<Selector>>>,
+}
+
+impl TxWhitelist {
+    /// Build TxWhitelist from config, entries with the same `to` are merged
+    pub fn from_config(entries: &[TxWhitelistEntryConfig]) -> Self {
+        let mut map: HashMap<Address, HashSet<Selector>> = HashMap::new();
+        for entry in entries {
+            map.entry(entry.to)
+                .or_default()
+                .extend(entry.selectors.iter().copied());
+        }
+
+        Self {
+            entries: Arc::new(map),
+        }
+    }
+
+    /// Check whether calling `selector` on contract `to` is allowed
+    pub fn check(&self, to: &Address, selector: &Selector) -> RpcResult<()> {
+        match self.entries.get(to) {
+            Some(selectors) if selectors.contains(selector) => Ok(()),
+            _ => Err(not_whitelisted(to, selector)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{address, fixed_bytes};
+
+    #[test]
+    fn test_tx_whitelist_check() {
+        let token = address!("0x1234567890abcdef1234567890abcdef12345678");
+        let other = address!("0xabcdefabcdefabcdefabcdefabcdefabcdefabcd");
+        let transfer = fixed_bytes!("0xa9059cbb");
+        let approve = fixed_bytes!("0x095ea7b3");
+
+        let whitelist = TxWhitelist::from_config(&[
+            TxWhitelistEntryConfig {
+                to: token,
+                selectors: vec![transfer],
+            },
+            TxWhitelistEntryConfig {
+                to: token,
+                selectors: vec![approve],
+            },
+        ]);
+
+        // Entries for the same contract are merged
+        assert!(whitelist.check(&token, &transfer).is_ok());
+        assert!(whitelist.check(&token, &approve).is_ok());
+
+        // Unknown selector on a whitelisted contract
+        let err = whitelist
+            .check(&token, &fixed_bytes!("0xdeadbeef"))
+            .unwrap_err();
+        assert!(err.data().unwrap().get().contains("0xdeadbeef"));
+
+        // Whitelisted selector on an unknown contract
+        let err = whitelist.check(&other, &transfer).unwrap_err();
+        assert!(err.data().unwrap().get().contains(&other.to_string()));
+    }
+}
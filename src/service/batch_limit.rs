@@ -0,0 +1,86 @@
+use futures_util::future::{Either, ready};
+use jsonrpsee::core::middleware::{Batch, Notification, RpcServiceT};
+use jsonrpsee::core::server::MethodResponse;
+use jsonrpsee::types::error::reject_too_big_batch_request;
+use jsonrpsee::types::{Id, Request};
+use serde::Deserialize;
+
+use crate::auth::AccessLevel;
+
+/// Maximum number of calls in a batch request per access level, unlimited if not set
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub struct MaxBatchSize {
+    pub none: Option<usize>,
+    pub basic: Option<usize>,
+    pub scoped: Option<usize>,
+    pub full: Option<usize>,
+}
+
+impl MaxBatchSize {
+    /// The batch size limit of `access`
+    pub fn limit(&self, access: &AccessLevel) -> Option<usize> {
+        match access {
+            AccessLevel::None => self.none,
+            AccessLevel::Basic(_) => self.basic,
+            AccessLevel::Scoped(_) => self.scoped,
+            AccessLevel::Full => self.full,
+        }
+    }
+}
+
+/// Rejects batch requests with more calls than allowed for the access level of the request
+#[derive(Clone)]
+pub struct BatchLimitMiddleware<S> {
+    service: S,
+    max_batch_size: MaxBatchSize,
+}
+
+impl<S> BatchLimitMiddleware<S> {
+    pub fn new(service: S, max_batch_size: MaxBatchSize) -> Self {
+        Self {
+            service,
+            max_batch_size,
+        }
+    }
+}
+
+impl<S> RpcServiceT for BatchLimitMiddleware<S>
+where
+    S: RpcServiceT<BatchResponse = MethodResponse> + Send + Sync + Clone + 'static,
+{
+    type MethodResponse = S::MethodResponse;
+    type NotificationResponse = S::NotificationResponse;
+    type BatchResponse = S::BatchResponse;
+
+    fn call<'a>(&self, req: Request<'a>) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+        self.service.call(req)
+    }
+
+    fn batch<'a>(
+        &self,
+        mut batch: Batch<'a>,
+    ) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+        let access = batch.extensions().get::<AccessLevel>().cloned();
+        let access = access.unwrap_or(AccessLevel::None);
+
+        if let Some(limit) = self.max_batch_size.limit(&access)
+            && batch.len() > limit
+        {
+            debug!(
+                "batch of {} calls rejected, limit is {limit} (with access = {access:?})",
+                batch.len()
+            );
+            let response = MethodResponse::error(Id::Null, reject_too_big_batch_request(limit));
+            return Either::Left(ready(response));
+        }
+
+        Either::Right(self.service.batch(batch))
+    }
+
+    fn notification<'a>(
+        &self,
+        n: Notification<'a>,
+    ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+        self.service.notification(n)
+    }
+}
@@ -0,0 +1,204 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use alloy::primitives::Address;
+use hyper::StatusCode;
+use jsonrpsee::http_client::{HttpBody, HttpResponse};
+use moka::future::Cache;
+
+use super::http_routes::RouteHandler;
+use crate::auth::JwtSigner;
+use crate::proxy::RpcProxyImpl;
+
+/// Time allowed for the readiness checks, probes usually time out after a few seconds
+const READY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The result of the readiness checks is reused for this long, so probes don't load the upstreams
+const READY_CACHE_SECS: u64 = 3;
+
+/// Handler of `GET /health`, the process is alive as long as it answers
+pub fn liveness_route() -> RouteHandler {
+    Arc::new(|| Box::pin(async { text_response(StatusCode::OK, "ok".to_string()) }))
+}
+
+/// Checks of `GET /ready`: the server is not shutting down, both upstreams answer, the validium
+/// chain id is the expected one and access tokens can be signed and verified.
+#[derive(Clone)]
+pub struct ReadinessCheck {
+    proxy: RpcProxyImpl,
+    jwt: JwtSigner,
+    // Expected validium chain id, `siwe.chain_id`
+    chain_id: Option<u64>,
+    // Set on shutdown, so that no new requests are routed to the server
+    draining: Arc<AtomicBool>,
+    // Result of the last checks, a single entry
+    last_result: Cache<(), bool>,
+}
+
+impl ReadinessCheck {
+    pub fn new(proxy: RpcProxyImpl, jwt: JwtSigner, chain_id: Option<u64>) -> Self {
+        Self {
+            proxy,
+            jwt,
+            chain_id,
+            draining: Arc::new(AtomicBool::new(false)),
+            last_result: Cache::builder()
+                .time_to_live(Duration::from_secs(READY_CACHE_SECS))
+                .build(),
+        }
+    }
+
+    /// Report not ready from now on, the server is shutting down
+    pub fn set_draining(&self) {
+        self.draining.store(true, Ordering::Relaxed);
+    }
+
+    pub async fn check(&self) -> anyhow::Result<()> {
+        if self.draining.load(Ordering::Relaxed) {
+            anyhow::bail!("shutting down");
+        }
+
+        let exp = (chrono::Utc::now().timestamp() + 60) as usize;
+        let token = self.jwt.create_token(Address::ZERO, exp)?;
+        self.jwt.decode_token(token)?;
+
+        let chain_id = self.proxy.validium_chain_id().await?;
+        if let Some(expected) = self.chain_id
+            && chain_id != expected
+        {
+            anyhow::bail!("validium chain id {chain_id} does not match {expected}");
+        }
+
+        self.proxy
+            .check_withdraw_proofs()
+            .await
+            .map_err(|e| anyhow::anyhow!("withdraw proofs: {e}"))
+    }
+
+    /// Run the checks at most once every `READY_CACHE_SECS`, the reason of a failure is logged.
+    /// Draining is reported right away.
+    pub async fn is_ready(&self) -> bool {
+        if self.draining.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        self.last_result
+            .get_with((), async {
+                let result = tokio::time::timeout(READY_TIMEOUT, self.check())
+                    .await
+                    .unwrap_or_else(|_| Err(anyhow::anyhow!("checks timed out")));
+                result.inspect_err(|e| warn!("Not ready: {e}")).is_ok()
+            })
+            .await
+    }
+
+    /// Handler of `GET /ready`, `503 Service Unavailable` if a check fails
+    pub fn route(self) -> RouteHandler {
+        Arc::new(move || {
+            let readiness = self.clone();
+            Box::pin(async move {
+                if readiness.is_ready().await {
+                    text_response(StatusCode::OK, "ready".to_string())
+                } else {
+                    text_response(StatusCode::SERVICE_UNAVAILABLE, "not ready".to_string())
+                }
+            })
+        })
+    }
+}
+
+fn text_response(status: StatusCode, body: String) -> HttpResponse {
+    http::Response::builder()
+        .status(status)
+        .header(hyper::header::CONTENT_TYPE, "text/plain")
+        .body(HttpBody::from(body))
+        .expect("valid response")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpsee::RpcModule;
+    use jsonrpsee::server::ServerHandle;
+    use std::collections::HashSet;
+
+    use crate::auth::{AccessPolicy, JwtSignerKeyConfig};
+    use crate::proxy::{CallAllowlist, TxWhitelist};
+    use crate::test_utils::mock_server;
+
+    /// Upstream answering `eth_chainId` only
+    async fn mock_upstream() -> (String, ServerHandle) {
+        let mut module = RpcModule::new(());
+        module
+            .register_method("eth_chainId", |_, _, _| "0x1")
+            .unwrap();
+        mock_server(module).await
+    }
+
+    fn readiness(
+        validium_url: &str,
+        withdraw_proofs_url: &str,
+        chain_id: Option<u64>,
+    ) -> ReadinessCheck {
+        let proxy = RpcProxyImpl::new(
+            validium_url,
+            withdraw_proofs_url,
+            TxWhitelist::from_config(&[]),
+            CallAllowlist::default(),
+            Arc::new(AccessPolicy::default()),
+            10_000,
+            false,
+            HashSet::new(),
+        )
+        .unwrap();
+        let jwt = JwtSigner::from_config(
+            &[JwtSignerKeyConfig {
+                kid: "test".to_string(),
+                secret: "testsecret-testsecret-testsecret".to_string(),
+                ..Default::default()
+            }],
+            "test",
+        )
+        .unwrap();
+        ReadinessCheck::new(proxy, jwt, chain_id)
+    }
+
+    #[tokio::test]
+    async fn test_readiness() {
+        let (url, _handle) = mock_upstream().await;
+
+        // Errors of the withdraw-proofs service (method not found) still mean it is reachable
+        assert!(readiness(&url, &url, Some(1)).check().await.is_ok());
+
+        let err = readiness(&url, &url, Some(2)).check().await.unwrap_err();
+        assert!(err.to_string().contains("does not match"));
+        // No chain id without a `[siwe]` section
+        assert!(readiness(&url, &url, None).check().await.is_ok());
+
+        let unreachable = "http://127.0.0.1:1";
+        assert!(readiness(unreachable, &url, Some(1)).check().await.is_err());
+        let err = readiness(&url, unreachable, Some(1))
+            .check()
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("withdraw proofs"));
+
+        // The result is reused for a few seconds
+        let check = readiness(&url, &url, Some(1));
+        assert!(check.is_ready().await);
+        let (other_url, other_handle) = mock_upstream().await;
+        let check_other = readiness(&other_url, &other_url, Some(1));
+        assert!(check_other.is_ready().await);
+        other_handle.stop().unwrap();
+        other_handle.stopped().await;
+        assert!(check_other.check().await.is_err());
+        assert!(check_other.is_ready().await);
+
+        // Not ready once shutting down, also for clones
+        check.clone().set_draining();
+        let err = check.check().await.unwrap_err();
+        assert_eq!(err.to_string(), "shutting down");
+        assert!(!check.is_ready().await);
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures_util::future::BoxFuture;
+use hyper::Method;
+use jsonrpsee::http_client::{HttpBody, HttpRequest, HttpResponse};
+use tower::{Layer, Service};
+
+/// Handler producing the response of a plain HTTP `GET` route
+pub type RouteHandler = Arc<dyn Fn() -> BoxFuture<'static, HttpResponse> + Send + Sync>;
+
+/// Serves plain HTTP `GET` routes (e.g. `/.well-known/jwks.json`) next to the JSON-RPC API,
+/// all other requests are passed to the RPC server.
+#[derive(Clone, Default)]
+pub struct HttpRoutesLayer {
+    routes: HashMap<String, RouteHandler>,
+}
+
+impl HttpRoutesLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve `handler` on `GET path`
+    pub fn route(mut self, path: impl Into<String>, handler: RouteHandler) -> Self {
+        self.routes.insert(path.into(), handler);
+        self
+    }
+
+    /// Serve a fixed JSON document on `GET path`
+    pub fn json(self, path: impl Into<String>, body: String) -> Self {
+        self.route(
+            path,
+            Arc::new(move || {
+                let body = body.clone();
+                Box::pin(async move { json_response(body) })
+            }),
+        )
+    }
+
+    /// Serve a document rendered on each request on `GET path`
+    pub fn render(
+        self,
+        path: impl Into<String>,
+        content_type: &'static str,
+        render: impl Fn() -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.route(
+            path,
+            Arc::new(move || {
+                let body = render();
+                Box::pin(async move { response(content_type, body) })
+            }),
+        )
+    }
+}
+
+impl<S> Layer<S> for HttpRoutesLayer {
+    type Service = HttpRoutes<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        HttpRoutes {
+            inner,
+            routes: Arc::new(self.routes.clone()),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct HttpRoutes<S> {
+    inner: S,
+    routes: Arc<HashMap<String, RouteHandler>>,
+}
+
+impl<S> Service<HttpRequest> for HttpRoutes<S>
+where
+    S: Service<HttpRequest, Response = HttpResponse>,
+    S::Future: Send + 'static,
+{
+    type Response = HttpResponse;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<HttpResponse, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: HttpRequest) -> Self::Future {
+        if request.method() == Method::GET
+            && let Some(handler) = self.routes.get(request.uri().path())
+        {
+            let response = handler();
+            return Box::pin(async move { Ok(response.await) });
+        }
+
+        Box::pin(self.inner.call(request))
+    }
+}
+
+/// Build a `200 OK` response with a JSON body
+pub fn json_response(body: String) -> HttpResponse {
+    response("application/json", body)
+}
+
+fn response(content_type: &str, body: String) -> HttpResponse {
+    http::Response::builder()
+        .header(hyper::header::CONTENT_TYPE, content_type)
+        .body(HttpBody::from(body))
+        .expect("valid response")
+}
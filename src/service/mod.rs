@@ -1,5 +1,13 @@
+mod batch_limit;
+mod health;
 mod http_logger;
+mod http_routes;
 mod rpc_logger;
+mod rpc_metrics;
 
+pub use batch_limit::{BatchLimitMiddleware, MaxBatchSize};
+pub use health::{ReadinessCheck, liveness_route};
 pub use http_logger::log_request;
+pub use http_routes::HttpRoutesLayer;
 pub use rpc_logger::RpcLoggerMiddleware;
+pub use rpc_metrics::RpcMetricsMiddleware;
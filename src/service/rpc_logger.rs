@@ -19,7 +19,11 @@ impl<S> RpcLoggerMiddleware<S> {
 impl<S> RpcServiceT for RpcLoggerMiddleware<S>
 where
     // Use the concrete MethodResponse type so that we can access the inner json.
-    S: RpcServiceT<MethodResponse = MethodResponse> + Send + Sync + Clone + 'static,
+    S: RpcServiceT<MethodResponse = MethodResponse, BatchResponse = MethodResponse>
+        + Send
+        + Sync
+        + Clone
+        + 'static,
 {
     type MethodResponse = S::MethodResponse;
     type NotificationResponse = S::NotificationResponse;
@@ -46,7 +50,31 @@ where
     }
 
     fn batch<'a>(&self, batch: Batch<'a>) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
-        self.service.batch(batch)
+        // log each entry of the batch, they are not logged by `call`
+        for (i, entry) in batch.iter().enumerate() {
+            match entry {
+                Ok(entry) => {
+                    let params = match entry.params() {
+                        None => "".to_owned(),
+                        Some(p) => p.to_string(),
+                    };
+                    let access = entry.extensions().get::<AccessLevel>();
+                    info!(
+                        "rpc batch request #{i}: {}({}) (with access = {:?})",
+                        entry.method_name(),
+                        params,
+                        access
+                    );
+                }
+                Err(_) => info!("rpc batch request #{i}: invalid"),
+            }
+        }
+
+        // execute, and log response
+        self.service.batch(batch).map(|resp| {
+            info!("rpc batch response: {:}", resp.to_string());
+            resp
+        })
     }
     fn notification<'a>(
         &self,
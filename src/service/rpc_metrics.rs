@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use futures_util::FutureExt;
+use jsonrpsee::core::middleware::{Batch, BatchEntry, Notification, RpcServiceT};
+use jsonrpsee::core::server::MethodResponse;
+use jsonrpsee::types::{Id, Request};
+use serde::Deserialize;
+
+use crate::auth::AccessPolicy;
+use crate::metrics::metrics;
+
+/// Counts and times RPC calls, including each call of a batch
+#[derive(Clone)]
+pub struct RpcMetricsMiddleware<S> {
+    service: S,
+    // every registered method has a rule, other names are not used as labels
+    policy: Arc<AccessPolicy>,
+}
+
+impl<S> RpcMetricsMiddleware<S> {
+    pub fn new(service: S, policy: Arc<AccessPolicy>) -> Self {
+        Self { service, policy }
+    }
+
+    /// The method label of a call, `None` for methods that are not registered
+    fn method_label(&self, method: &str) -> Option<String> {
+        self.policy.rule(method).map(|_| method.to_owned())
+    }
+}
+
+/// An entry of a batch response, only the id and error code are needed
+#[derive(Deserialize)]
+struct BatchResponseEntry<'a> {
+    #[serde(borrow)]
+    id: Id<'a>,
+    error: Option<BatchResponseError>,
+}
+
+#[derive(Deserialize)]
+struct BatchResponseError {
+    code: i32,
+}
+
+/// The error codes of the entries of a batch response by id,
+/// `None` if the whole batch was rejected with a single error
+fn batch_error_codes(resp: &MethodResponse) -> Option<HashMap<Id<'_>, i32>> {
+    let entries: Vec<BatchResponseEntry> = serde_json::from_str(resp.as_json().get()).ok()?;
+    Some(
+        entries
+            .into_iter()
+            .filter_map(|entry| Some((entry.id, entry.error?.code)))
+            .collect(),
+    )
+}
+
+impl<S> RpcServiceT for RpcMetricsMiddleware<S>
+where
+    S: RpcServiceT<MethodResponse = MethodResponse, BatchResponse = MethodResponse>
+        + Send
+        + Sync
+        + Clone
+        + 'static,
+{
+    type MethodResponse = S::MethodResponse;
+    type NotificationResponse = S::NotificationResponse;
+    type BatchResponse = S::BatchResponse;
+
+    fn call<'a>(&self, req: Request<'a>) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+        let method = self.method_label(req.method_name());
+        let started = Instant::now();
+
+        self.service.call(req).map(move |resp| {
+            metrics().record_rpc(method.as_deref(), resp.as_error_code(), started.elapsed());
+            resp
+        })
+    }
+
+    fn batch<'a>(&self, batch: Batch<'a>) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+        // the calls of the batch, they are not recorded by `call`
+        let calls: Vec<_> = batch
+            .iter()
+            .filter_map(|entry| match entry {
+                Ok(BatchEntry::Call(req)) => Some((
+                    req.id.clone().into_owned(),
+                    self.method_label(req.method_name()),
+                )),
+                _ => None,
+            })
+            .collect();
+        let started = Instant::now();
+
+        self.service.batch(batch).map(move |resp| {
+            // calls of a batch are timed with the whole batch
+            let elapsed = started.elapsed();
+            let codes = batch_error_codes(&resp);
+            for (id, method) in calls {
+                let code = match &codes {
+                    Some(codes) => codes.get(&id).copied(),
+                    None => resp.as_error_code(),
+                };
+                metrics().record_rpc(method.as_deref(), code, elapsed);
+            }
+            resp
+        })
+    }
+
+    fn notification<'a>(
+        &self,
+        n: Notification<'a>,
+    ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+        self.service.notification(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpsee::core::server::{BatchResponseBuilder, ResponsePayload};
+    use jsonrpsee::types::ErrorObject;
+    use jsonrpsee::types::error::{INVALID_REQUEST_CODE, METHOD_NOT_FOUND_CODE};
+
+    #[test]
+    fn test_batch_error_codes() {
+        let mut batch = BatchResponseBuilder::new_with_limit(usize::MAX);
+        batch
+            .append(MethodResponse::response(
+                Id::Number(1),
+                ResponsePayload::success("0x1"),
+                usize::MAX,
+            ))
+            .unwrap();
+        batch
+            .append(MethodResponse::error(
+                Id::Str("a".into()),
+                ErrorObject::borrowed(METHOD_NOT_FOUND_CODE, "Method not found", None),
+            ))
+            .unwrap();
+        let resp = MethodResponse::from_batch(batch.finish());
+        let codes = batch_error_codes(&resp).unwrap();
+        assert_eq!(codes.get(&Id::Number(1)), None);
+        assert_eq!(
+            codes.get(&Id::Str("a".into())),
+            Some(&METHOD_NOT_FOUND_CODE)
+        );
+
+        // The whole batch was rejected
+        let resp = MethodResponse::error(
+            Id::Null,
+            ErrorObject::borrowed(INVALID_REQUEST_CODE, "Batch too large", None),
+        );
+        assert!(batch_error_codes(&resp).is_none());
+    }
+}
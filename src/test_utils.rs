@@ -0,0 +1,10 @@
+use jsonrpsee::RpcModule;
+use jsonrpsee::server::{Server, ServerHandle};
+
+/// Serve `module` on a free local port, e.g. as a mock upstream.
+/// Returns the URL of the server, it stops when the handle is dropped.
+pub async fn mock_server(module: RpcModule<()>) -> (String, ServerHandle) {
+    let server = Server::builder().build("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", server.local_addr().unwrap());
+    (url, server.start(module))
+}